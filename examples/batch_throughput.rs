@@ -0,0 +1,115 @@
+// Compares proving many Fibonacci sequences one proof at a time against proving them
+// all in a single batched proof.
+//
+//   cargo run --release --example batch_throughput -- [sequences] [steps]
+
+use std::time::{Duration, Instant};
+
+use halo2_examples::{
+    fibonacci::{batch::BatchCircuit, PublicInputs, ThreeColumnFiboCircuit},
+    instance::InstanceColumn,
+    prove,
+};
+use halo2_proofs::{circuit::Value, pasta::Fp, plonk::Circuit};
+
+const GROUPS: usize = 4;
+
+struct Run {
+    k: u32,
+    proofs: usize,
+    proof_bytes: usize,
+    keygen: Duration,
+    prove: Duration,
+    verify: Duration,
+}
+
+fn report(name: &str, sequences: usize, run: &Run) {
+    let total = run.keygen + run.prove + run.verify;
+    println!(
+        "{:<12} k = {:<2}  proofs = {:<5}  bytes = {:<8}  keygen = {:>8.2?}  prove = {:>9.2?}  verify = {:>8.2?}  {:>8.1} sequences/s",
+        name,
+        run.k,
+        run.proofs,
+        run.proof_bytes,
+        run.keygen,
+        run.prove,
+        run.verify,
+        sequences as f64 / total.as_secs_f64(),
+    );
+}
+
+fn one_by_one(seeds: &[(Fp, Fp)], steps: usize) -> Run {
+    let shape = ThreeColumnFiboCircuit::<Fp> { steps, ..Default::default() };
+    let k = shape.k().unwrap();
+    let params = prove::params(k);
+
+    let start = Instant::now();
+    let pk = prove::keygen(&params, &shape.without_witnesses()).unwrap();
+    let keygen = start.elapsed();
+
+    let start = Instant::now();
+    let proofs: Vec<_> = seeds
+        .iter()
+        .map(|&(a, b)| {
+            let circuit = ThreeColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+            let instances = PublicInputs::new(a, b, steps).instances();
+            (prove::prove(&params, &pk, circuit, &instances).unwrap(), instances)
+        })
+        .collect();
+    let prove = start.elapsed();
+
+    let start = Instant::now();
+    for (proof, instances) in &proofs {
+        assert!(prove::verify(&params, pk.get_vk(), proof, instances).is_valid());
+    }
+    let verify = start.elapsed();
+
+    Run {
+        k,
+        proofs: proofs.len(),
+        proof_bytes: proofs.iter().map(|(proof, _)| proof.len()).sum(),
+        keygen,
+        prove,
+        verify,
+    }
+}
+
+fn batched(seeds: &[(Fp, Fp)], steps: usize) -> Run {
+    let circuit = BatchCircuit::<Fp, GROUPS>::new(seeds, steps);
+    let instances = BatchCircuit::<Fp, GROUPS>::instances(seeds, steps);
+    let k = circuit.k().unwrap();
+    let params = prove::params(k);
+
+    let start = Instant::now();
+    let pk = prove::keygen(&params, &circuit.without_witnesses()).unwrap();
+    let keygen = start.elapsed();
+
+    let start = Instant::now();
+    let proof = prove::prove(&params, &pk, circuit, &instances).unwrap();
+    let prove = start.elapsed();
+
+    let start = Instant::now();
+    assert!(prove::verify(&params, pk.get_vk(), &proof, &instances).is_valid());
+    let verify = start.elapsed();
+
+    Run {
+        k,
+        proofs: 1,
+        proof_bytes: proof.len(),
+        keygen,
+        prove,
+        verify,
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|arg| arg.parse::<usize>().expect("arguments must be numbers"));
+    let sequences = args.next().unwrap_or(64);
+    let steps = args.next().unwrap_or(16);
+
+    let seeds: Vec<_> = (0..sequences as u64).map(|i| (Fp::from(i), Fp::from(i + 1))).collect();
+
+    println!("{} sequences of {} steps, {} column groups when batched", sequences, steps, GROUPS);
+    report("one by one", sequences, &one_by_one(&seeds, steps));
+    report("batched", sequences, &batched(&seeds, steps));
+}
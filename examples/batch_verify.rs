@@ -0,0 +1,51 @@
+// Compares verifying many proofs of the same circuit one at a time against verifying
+// them together with `prove::Batch`.
+//
+//   cargo run --release --example batch_verify -- [proofs] [steps]
+
+use std::time::Instant;
+
+use halo2_examples::{
+    fibonacci::{PublicInputs, ThreeColumnFiboCircuit},
+    instance::InstanceColumn,
+    prove::{self, Batch},
+};
+use halo2_proofs::{circuit::Value, pasta::Fp, plonk::Circuit};
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|arg| arg.parse::<usize>().expect("arguments must be numbers"));
+    let count = args.next().unwrap_or(128);
+    let steps = args.next().unwrap_or(16);
+
+    let shape = ThreeColumnFiboCircuit::<Fp> { steps, ..Default::default() };
+    let params = prove::params(shape.k().unwrap());
+    let pk = prove::keygen(&params, &shape.without_witnesses()).unwrap();
+
+    let start = Instant::now();
+    let proofs: Vec<_> = (0..count as u64)
+        .map(|i| {
+            let (a, b) = (Fp::from(i), Fp::from(i + 1));
+            let circuit = ThreeColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+            let instances = PublicInputs::new(a, b, steps).instances();
+            (prove::prove(&params, &pk, circuit, &instances).unwrap(), instances)
+        })
+        .collect();
+    println!("proved {} proofs of {} steps in {:.2?}", count, steps, start.elapsed());
+
+    let start = Instant::now();
+    for (proof, instances) in &proofs {
+        assert!(prove::verify(&params, pk.get_vk(), proof, instances).is_valid());
+    }
+    let sequential = start.elapsed();
+
+    let start = Instant::now();
+    let mut batch = Batch::new();
+    for (proof, instances) in proofs {
+        batch.add(proof, instances);
+    }
+    assert!(batch.verify(&params, pk.get_vk()).is_valid());
+    let batched = start.elapsed();
+
+    println!("sequential {:>9.2?}  {:>8.2?} per proof", sequential, sequential / count as u32);
+    println!("batched    {:>9.2?}  {:>8.2?} per proof", batched, batched / count as u32);
+}
@@ -0,0 +1,23 @@
+// Prints the cost report of every example circuit as a table, or as JSON with --json.
+//
+//   cargo run --example cost_report -- [steps] [--json]
+
+use halo2_examples::cost;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let steps = args
+        .iter()
+        .find(|arg| *arg != "--json")
+        .map(|arg| arg.parse::<usize>().expect("steps must be a number"))
+        .unwrap_or(16);
+
+    let reports = cost::all(steps).expect("circuit too large");
+    if json {
+        println!("{}", cost::to_json(&reports));
+    } else {
+        println!("{} steps", steps);
+        print!("{}", cost::table(&reports));
+    }
+}
@@ -0,0 +1,60 @@
+// Prints the rows every example circuit uses under SimpleFloorPlanner and V1.
+//
+//   cargo run --example planner_rows -- [steps]
+
+use halo2_examples::{
+    fibonacci::{
+        batch::BatchCircuit,
+        doubling::DoublingCircuit,
+        higher_order::{HigherOrderCircuit, Tribonacci},
+        integer::IntegerFiboCircuit,
+        private_index::PrivateIndexCircuit,
+        recurrence::Preset,
+        segment::SegmentCircuit,
+        wide::WideFiboCircuit,
+        SingleColumnFiboCircuit, ThreeColumnFiboCircuit,
+    },
+    is_zero,
+    planner::{row_counts, RowCounts},
+    range_check,
+};
+use halo2_proofs::{pasta::Fp, plonk::Error};
+
+fn main() -> Result<(), Error> {
+    let steps = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse::<usize>().expect("steps must be a number"))
+        .unwrap_or(16);
+
+    // Shapes only: the layout does not depend on the witness
+    let rows: Vec<(&str, RowCounts)> = vec![
+        ("fib3col", row_counts(&ThreeColumnFiboCircuit::<Fp> { steps, ..Default::default() })?),
+        ("fib1col", row_counts(&SingleColumnFiboCircuit::<Fp> { steps, ..Default::default() })?),
+        ("wide<6>", row_counts(&WideFiboCircuit::<Fp, 6> { steps, ..Default::default() })?),
+        ("doubling", row_counts(&DoublingCircuit::<Fp>::new(0, 16))?),
+        ("private-index", row_counts(&PrivateIndexCircuit::<Fp> { max: steps, ..Default::default() })?),
+        ("recurrence", row_counts(&Preset::Pell.circuit::<Fp>(steps))?),
+        ("tribonacci", row_counts(&HigherOrderCircuit::<Fp, Tribonacci, 3>::new(steps))?),
+        ("batch<4>", row_counts(&BatchCircuit::<Fp, 4>::new(&[(Fp::zero(), Fp::one()); 8], steps))?),
+        ("segment", row_counts(&SegmentCircuit::<Fp> { steps, ..Default::default() })?),
+        ("integer<4>", row_counts::<Fp, _>(&IntegerFiboCircuit::<4> { steps, ..Default::default() })?),
+        ("is-zero", row_counts(&is_zero::example1::MyCircuit::<Fp>::default())?),
+        ("range-expr", row_counts(&range_check::example1::MyCircuit::<Fp, 8>::default())?),
+        ("range-lookup", row_counts(&range_check::example2::MyCircuit::<Fp, 8, 256>::default())?),
+        (
+            "decompose-64",
+            row_counts(&range_check::decomposition::DecompositionCircuit::<Fp, 256> { num_bits: 64, ..Default::default() })?,
+        ),
+        (
+            "interval",
+            row_counts(&range_check::interval::IntervalCircuit::<Fp, 256>::new(0, 18, 121, Default::default(), Default::default()).unwrap())?,
+        ),
+    ];
+
+    println!("{} steps", steps);
+    println!("{:>13} | {:>6} | {:>6}", "circuit", "simple", "v1");
+    for (name, counts) in rows {
+        println!("{:>13} | {:>6} | {:>6}", name, counts.simple, counts.v1);
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+// Prints rows, columns and permutation cost of the configurable width Fibonacci circuit
+// for a range of widths.
+//
+//   cargo run --example wide_report -- [steps]
+
+use halo2_examples::fibonacci::wide::{report, WideReport};
+
+fn main() {
+    let steps = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse::<usize>().expect("steps must be a number"))
+        .unwrap_or(1000);
+
+    let reports: Vec<WideReport> = [
+        report::<3>(steps),
+        report::<4>(steps),
+        report::<6>(steps),
+        report::<10>(steps),
+        report::<18>(steps),
+        report::<34>(steps),
+    ]
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .expect("could not measure the circuit");
+
+    println!("{} steps", steps);
+    println!("{:>5} | {:>3} | {:>6} | {:>6} | {:>11} | {:>6} | {:>11}", "width", "k", "rows", "advice", "permutation", "copies", "proof bytes");
+    for r in reports {
+        println!(
+            "{:>5} | {:>3} | {:>6} | {:>6} | {:>11} | {:>6} | {:>11}",
+            r.width, r.k, r.rows, r.advice_columns, r.permutation_columns, r.copy_constraints, r.proof_bytes
+        );
+    }
+}
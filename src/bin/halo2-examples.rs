@@ -0,0 +1,304 @@
+// Command-line entry point for the example circuits.
+//
+//   halo2-examples mock fib3col --steps 20
+//   halo2-examples prove fib1col --a 2 --b 3 --out fib.proof
+//   halo2-examples verify fib1col --a 2 --b 3 --proof fib.proof
+//   halo2-examples cost range-lookup
+//   halo2-examples witness fib3col --steps 5
+//   halo2-examples min-k range-lookup
+//   halo2-examples layout is-zero --out is-zero.svg   (needs --features dev-graph)
+//
+// Every command prints a single JSON object on stdout.
+
+use std::{error::Error, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand};
+use halo2_examples::{
+    circuits::{CircuitKind, CircuitVisitor, Inputs},
+    cost,
+    envelope::Envelope,
+    prove::{self, Verification},
+    sizing,
+};
+use halo2_proofs::{
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{keygen_vk, Circuit},
+};
+use serde_json::{json, Value};
+
+#[derive(Parser)]
+#[command(name = "halo2-examples", about = "Mock, prove, verify and inspect the example halo2 circuits")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the constraints with the MockProver
+    Mock(CircuitArgs),
+    /// Create a real proof and write it to a proof envelope
+    Prove {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        /// Where to write the proof envelope
+        #[arg(long, default_value = "proof.bin")]
+        out: PathBuf,
+    },
+    /// Verify a proof envelope against the circuit
+    Verify {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        /// Proof envelope written by `prove`
+        #[arg(long, default_value = "proof.bin")]
+        proof: PathBuf,
+    },
+    /// Render the circuit layout to a PNG or SVG image, or export it as a DOT graph
+    Layout {
+        #[command(flatten)]
+        circuit: CircuitArgs,
+        /// Output file; the format is taken from its extension (.png, .svg or .dot)
+        #[arg(long, default_value = "layout.png")]
+        out: PathBuf,
+    },
+    /// Report columns, gates, lookups, rows and the estimated proof size of the circuit
+    Cost(CircuitArgs),
+    /// Print the witness values the circuit assigns, without running any prover
+    Witness(CircuitArgs),
+    /// Find the smallest k the MockProver accepts and why every smaller k fails
+    MinK(CircuitArgs),
+}
+
+#[derive(Args)]
+struct CircuitArgs {
+    /// One of fib3col, fib1col, range-expr, range-lookup, is-zero
+    circuit: CircuitKind,
+    /// Number of Fibonacci steps
+    #[arg(long, default_value_t = Inputs::default().steps)]
+    steps: usize,
+    /// First input (Fibonacci seed, range-checked value, is-zero input)
+    #[arg(long, default_value_t = Inputs::default().a)]
+    a: u64,
+    /// Second input (Fibonacci seed, lookup-checked value, is-zero input)
+    #[arg(long, default_value_t = Inputs::default().b)]
+    b: u64,
+    /// Third input (is-zero output when a == b)
+    #[arg(long, default_value_t = Inputs::default().c)]
+    c: u64,
+    /// Circuit size; defaults to the smallest k the circuit fits in
+    #[arg(long)]
+    k: Option<u32>,
+}
+
+impl CircuitArgs {
+    fn inputs(&self) -> Inputs {
+        Inputs {
+            a: self.a,
+            b: self.b,
+            c: self.c,
+            steps: self.steps,
+        }
+    }
+
+    fn visit<V: CircuitVisitor>(&self, visitor: V) -> Result<V::Output, Box<dyn Error>> {
+        self.circuit.visit(&self.inputs(), KOverride { k: self.k, inner: visitor })?
+    }
+}
+
+// Replaces the automatically chosen k when --k is given. The circuit has to fit in the
+// given k, and the params for it have to exist.
+struct KOverride<V> {
+    k: Option<u32>,
+    inner: V,
+}
+
+impl<V: CircuitVisitor> CircuitVisitor for KOverride<V> {
+    type Output = Result<V::Output, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        let Some(requested) = self.k else {
+            return Ok(self.inner.visit(circuit, instances, k));
+        };
+        let max_k = sizing::max_k::<Fp, C>();
+        if requested < k || requested > max_k {
+            return Err(format!("k = {} is out of range, the circuit supports {} ..= {}", requested, k, max_k).into());
+        }
+        Ok(self.inner.visit(circuit, instances, requested))
+    }
+}
+
+fn field_json(values: &[Vec<Fp>]) -> Value {
+    json!(values
+        .iter()
+        .map(|column| column.iter().map(|v| format!("{:?}", v)).collect::<Vec<_>>())
+        .collect::<Vec<_>>())
+}
+
+struct Mock;
+
+impl CircuitVisitor for Mock {
+    type Output = Result<Value, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        let prover = MockProver::run(k, &circuit, instances.clone())?;
+        let failures: Vec<String> = match prover.verify() {
+            Ok(()) => vec![],
+            Err(failures) => failures.iter().map(|f| f.to_string()).collect(),
+        };
+        Ok(json!({
+            "k": k,
+            "instances": field_json(&instances),
+            "satisfied": failures.is_empty(),
+            "failures": failures,
+        }))
+    }
+}
+
+struct Prove {
+    id: &'static str,
+    out: PathBuf,
+}
+
+impl CircuitVisitor for Prove {
+    type Output = Result<Value, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        let params = prove::params(k);
+        let pk = prove::keygen(&params, &circuit.without_witnesses())?;
+        let proof = prove::prove(&params, &pk, circuit, &instances)?;
+        let proof_len = proof.len();
+
+        Envelope::new(self.id, k, params, pk.get_vk(), instances.clone(), proof).save(&self.out)?;
+        Ok(json!({
+            "k": k,
+            "instances": field_json(&instances),
+            "proof_bytes": proof_len,
+            "path": self.out.display().to_string(),
+        }))
+    }
+}
+
+struct Verify {
+    id: &'static str,
+    envelope: Envelope,
+}
+
+impl CircuitVisitor for Verify {
+    type Output = Result<Value, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        let envelope = self.envelope;
+        envelope.check(self.id, k)?;
+        // The proof has to be for the public inputs given on the command line
+        if envelope.instances != instances {
+            return Err("the public inputs in the envelope do not match the given arguments".into());
+        }
+
+        // The verifier generates its own params rather than trusting the sender's
+        let params = prove::params(k);
+        let vk = keygen_vk(&params, &circuit.without_witnesses())?;
+        envelope.check_vk(&vk)?;
+
+        let (valid, error) = match prove::verify(&params, &vk, &envelope.proof, &instances) {
+            Verification::Valid => (true, None),
+            Verification::Invalid(e) => (false, Some(format!("{:?}", e))),
+        };
+        Ok(json!({
+            "k": k,
+            "instances": field_json(&instances),
+            "valid": valid,
+            "error": error,
+        }))
+    }
+}
+
+struct Cost;
+
+impl CircuitVisitor for Cost {
+    type Output = Result<Value, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, _: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        Ok(cost::measure(&circuit, k)?.to_json())
+    }
+}
+
+// Largest k `min-k` tries
+const SEARCH_MAX_K: u32 = 18;
+
+struct MinK;
+
+impl CircuitVisitor for MinK {
+    type Output = Value;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, _: u32) -> Self::Output {
+        let search = sizing::search_k(&circuit, &instances, SEARCH_MAX_K);
+        let rejected: Vec<Value> = search
+            .rejected
+            .iter()
+            .map(|(k, rejection)| json!({ "k": k, "reason": rejection.to_string() }))
+            .collect();
+        json!({ "k": search.k, "rejected": rejected })
+    }
+}
+
+fn run(command: Command) -> Result<Value, Box<dyn Error>> {
+    let (args, output) = match command {
+        Command::Mock(args) => {
+            let output = args.visit(Mock)??;
+            (args, output)
+        }
+        Command::Prove { circuit: args, out } => {
+            let output = args.visit(Prove { id: args.circuit.id(), out })??;
+            (args, output)
+        }
+        Command::Verify { circuit: args, proof } => {
+            let envelope = Envelope::load(&proof)?;
+            let output = args.visit(Verify { id: args.circuit.id(), envelope })??;
+            (args, output)
+        }
+        Command::Cost(args) => {
+            let output = args.visit(Cost)??;
+            (args, output)
+        }
+        Command::MinK(args) => {
+            let output = args.visit(MinK)?;
+            (args, output)
+        }
+        Command::Witness(args) => {
+            let output = json!({ "witness": args.circuit.witness(&args.inputs()) });
+            (args, output)
+        }
+        #[cfg(feature = "dev-graph")]
+        Command::Layout { circuit: args, out } => {
+            use halo2_examples::layout::{Format, Render};
+
+            let format = Format::from_path(&out).ok_or("the output file must end in .png, .svg or .dot")?;
+            // `visit` already applies --k, so the renderer keeps the k it is given
+            let k = args.visit(Render { path: &out, format, k: None })??;
+            (args, json!({ "k": k, "path": out.display().to_string(), "format": format.extension() }))
+        }
+        #[cfg(not(feature = "dev-graph"))]
+        Command::Layout { .. } => {
+            return Err("layout rendering needs the `dev-graph` feature (cargo run --features dev-graph)".into())
+        }
+    };
+
+    let mut output = output;
+    output["circuit"] = json!(args.circuit.id());
+    Ok(output)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("{}", json!({ "error": e.to_string() }));
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Catalogue of the example circuits, so tools can pick one by name.
+//
+// The circuits have different Rust types, so callers that want to do something
+// generic with "the selected circuit" implement `CircuitVisitor` and hand it to
+// `CircuitKind::visit`, which builds the circuit and its public inputs.
+
+use std::{fmt, str::FromStr};
+
+use halo2_proofs::{circuit::Value, pasta::Fp, plonk::Circuit};
+use serde_json::{json, Value as Json};
+
+use crate::{
+    fibonacci::{PublicInputs, SingleColumnFiboCircuit, ThreeColumnFiboCircuit},
+    instance::InstanceColumn,
+    is_zero, range_check,
+    sizing::{self, SizeError},
+    witness::{FibonacciWitness, IsZeroWitness, RangeCheckWitness},
+};
+
+// Range used by the expression-based range check
+pub const RANGE: usize = 8;
+// Size of the lookup table used by the lookup-based range check
+pub const LOOKUP_RANGE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitKind {
+    Fib3Col,
+    Fib1Col,
+    RangeExpr,
+    RangeLookup,
+    IsZero,
+}
+
+impl CircuitKind {
+    pub const ALL: [CircuitKind; 5] = [
+        CircuitKind::Fib3Col,
+        CircuitKind::Fib1Col,
+        CircuitKind::RangeExpr,
+        CircuitKind::RangeLookup,
+        CircuitKind::IsZero,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            CircuitKind::Fib3Col => "fib3col",
+            CircuitKind::Fib1Col => "fib1col",
+            CircuitKind::RangeExpr => "range-expr",
+            CircuitKind::RangeLookup => "range-lookup",
+            CircuitKind::IsZero => "is-zero",
+        }
+    }
+
+    // Builds the circuit with the given inputs and passes it to the visitor, together with
+    // its public inputs and the smallest k it fits in
+    pub fn visit<V: CircuitVisitor>(&self, inputs: &Inputs, visitor: V) -> Result<V::Output, SizeError> {
+        let (a, b, c) = (Fp::from(inputs.a), Fp::from(inputs.b), Fp::from(inputs.c));
+
+        match self {
+            CircuitKind::Fib3Col => {
+                let circuit = ThreeColumnFiboCircuit {
+                    a: Value::known(a),
+                    b: Value::known(b),
+                    steps: inputs.steps,
+                };
+                let k = circuit.k()?;
+                let public_inputs = PublicInputs::new(a, b, inputs.steps);
+                Ok(visitor.visit(circuit, public_inputs.instances(), k))
+            }
+            CircuitKind::Fib1Col => {
+                let circuit = SingleColumnFiboCircuit {
+                    a: Value::known(a),
+                    b: Value::known(b),
+                    steps: inputs.steps,
+                };
+                let k = circuit.k()?;
+                let public_inputs = PublicInputs::new(a, b, inputs.steps);
+                Ok(visitor.visit(circuit, public_inputs.instances(), k))
+            }
+            CircuitKind::RangeExpr => {
+                let circuit = range_check::example1::MyCircuit::<Fp, RANGE> {
+                    value: Value::known(a.into()),
+                };
+                let k = sizing::minimal_k::<Fp, range_check::example1::MyCircuit<Fp, RANGE>>(1)?;
+                Ok(visitor.visit(circuit, vec![], k))
+            }
+            CircuitKind::RangeLookup => {
+                let circuit = range_check::example2::MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
+                    value: Value::known(a.into()),
+                    large_value: Value::known(b.into()),
+                };
+                // The lookup table holds every width up to LOOKUP_RANGE
+                let k = sizing::minimal_k::<Fp, range_check::example2::MyCircuit<Fp, RANGE, LOOKUP_RANGE>>(
+                    range_check::example2::table::table_rows(LOOKUP_RANGE),
+                )?;
+                Ok(visitor.visit(circuit, vec![], k))
+            }
+            CircuitKind::IsZero => {
+                let circuit = is_zero::example1::MyCircuit {
+                    a: Value::known(a),
+                    b: Value::known(b),
+                    c: Value::known(c),
+                };
+                let k = sizing::minimal_k::<Fp, is_zero::example1::MyCircuit<Fp>>(1)?;
+                let public_inputs = is_zero::example1::PublicInputs::new(a, b, c);
+                Ok(visitor.visit(circuit, public_inputs.instances(), k))
+            }
+        }
+    }
+}
+
+impl CircuitKind {
+    // The values the circuit assigns, generated natively from the inputs
+    pub fn witness(&self, inputs: &Inputs) -> Json {
+        let (a, b, c) = (Fp::from(inputs.a), Fp::from(inputs.b), Fp::from(inputs.c));
+
+        match self {
+            CircuitKind::Fib3Col | CircuitKind::Fib1Col => FibonacciWitness::generate(a, b, inputs.steps).to_json(),
+            CircuitKind::RangeExpr => RangeCheckWitness::generate(a, RANGE).to_json(),
+            CircuitKind::RangeLookup => json!({
+                "value": RangeCheckWitness::generate(a, RANGE).to_json(),
+                "large_value": RangeCheckWitness::generate(b, LOOKUP_RANGE).to_json(),
+            }),
+            CircuitKind::IsZero => IsZeroWitness::generate(a, b, c).to_json(),
+        }
+    }
+}
+
+impl fmt::Display for CircuitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for CircuitKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CircuitKind::ALL.into_iter().find(|kind| kind.id() == s).ok_or_else(|| {
+            let known: Vec<_> = CircuitKind::ALL.iter().map(|kind| kind.id()).collect();
+            format!("unknown circuit `{}` (expected one of: {})", s, known.join(", "))
+        })
+    }
+}
+
+// Witness values shared by all circuits; each circuit reads the ones it needs:
+//  - fib3col, fib1col: seeds a and b, number of steps
+//  - range-expr: a is checked against RANGE
+//  - range-lookup: a is checked against RANGE, b against LOOKUP_RANGE
+//  - is-zero: a, b and c
+#[derive(Debug, Clone, Copy)]
+pub struct Inputs {
+    pub a: u64,
+    pub b: u64,
+    pub c: u64,
+    pub steps: usize,
+}
+
+impl Default for Inputs {
+    fn default() -> Self {
+        Inputs { a: 1, b: 1, c: 0, steps: 8 }
+    }
+}
+
+pub trait CircuitVisitor {
+    type Output;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> Self::Output;
+}
+
+// F(steps + 1) for the sequence F(0) = a, F(1) = b, computed natively
+pub fn fibonacci(a: Fp, b: Fp, steps: usize) -> Fp {
+    PublicInputs::new(a, b, steps).out
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+
+    use super::*;
+
+    struct Mock;
+
+    impl CircuitVisitor for Mock {
+        type Output = bool;
+
+        fn visit<C: Circuit<Fp>>(self, circuit: C, instances: Vec<Vec<Fp>>, k: u32) -> bool {
+            MockProver::run(k, &circuit, instances).unwrap().verify().is_ok()
+        }
+    }
+
+    #[test]
+    fn test_every_circuit_is_satisfied_by_default() {
+        for kind in CircuitKind::ALL {
+            assert_eq!(kind.id().parse::<CircuitKind>(), Ok(kind));
+            assert!(kind.visit(&Inputs::default(), Mock).unwrap(), "{} is not satisfied", kind);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_inputs_fail() {
+        let inputs = Inputs { a: RANGE as u64, ..Inputs::default() };
+        assert!(!CircuitKind::RangeExpr.visit(&inputs, Mock).unwrap());
+
+        let inputs = Inputs { b: LOOKUP_RANGE as u64, ..Inputs::default() };
+        assert!(!CircuitKind::RangeLookup.visit(&inputs, Mock).unwrap());
+    }
+}
@@ -0,0 +1,348 @@
+// Cost report for the example circuits.
+//
+// `measure` collects what decides prover and verifier cost before any key is generated:
+// the column counts, the gates and the degree of the constraint system, the lookups and
+// the permutation, the rows the layout takes and halo2's `CircuitCost` estimate of the
+// proof. `all` measures every circuit in the crate, and a list of reports prints as a
+// table or as JSON.
+//
+// halo2 0.2 keeps the column, selector and lookup counts of the constraint system private and
+// has no accessors for them. They are parsed from the `Debug` output of the pinned constraint
+// system, of `CircuitGates` and of the proof size estimate, so they rely on how halo2 formats
+// those types rather than on a stable API; a halo2 upgrade that changes the formatting shows
+// up as zero counts in the tests below.
+
+use std::fmt;
+
+use halo2_proofs::{
+    circuit::Value,
+    dev::{CircuitCost, CircuitGates},
+    pasta::{Eq, Fp},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use serde_json::{json, Value as Json};
+
+use crate::{
+    circuits::{CircuitKind, CircuitVisitor, Inputs},
+    fibonacci::{
+        batch::BatchCircuit,
+        doubling::DoublingCircuit,
+        higher_order::{HigherOrderCircuit, Tribonacci},
+        integer::IntegerFiboCircuit,
+        private_index::PrivateIndexCircuit,
+        recurrence::Preset,
+        segment::SegmentCircuit,
+        wide::WideFiboCircuit,
+    },
+    planner,
+    range_check::{
+        auto::AutoRangeCircuit,
+        decomposition::DecompositionCircuit,
+        interval::{BoundKind, IntervalCircuit},
+    },
+    sizing::SizeError,
+};
+
+// halo2's estimate of the proof, with the commitments and evaluations the verifier reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofCost {
+    pub bytes: usize,
+    // Bytes added by every further proof of the same circuit in one transcript
+    pub marginal_bytes: usize,
+    pub commitments: usize,
+    pub evaluations: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cost {
+    pub k: u32,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    // Selectors before keygen compresses them into fixed columns
+    pub selectors: usize,
+    pub gates: usize,
+    // Polynomial constraints over all gates
+    pub constraints: usize,
+    // Highest degree over the gates, the lookups and the permutation; the prover works on an
+    // extended domain of 2^k * (degree - 1) points
+    pub degree: usize,
+    pub lookups: usize,
+    pub permutation_columns: usize,
+    pub rows: usize,
+    // None for circuits without equality constraints, which halo2's model does not cover
+    pub proof: Option<ProofCost>,
+    // Rough size of the multiexponentiation the IPA verifier ends with, counted as
+    // 2^k + commitments + evaluations: the 2^k generators, every commitment read from the
+    // proof, and one term per evaluation when the openings are combined. This is an ad-hoc
+    // estimate, not something halo2 reports, and only meant to compare circuits with each other.
+    // None along with the proof estimate.
+    pub verifier_msm: Option<usize>,
+}
+
+impl Cost {
+    pub fn to_json(&self) -> Json {
+        json!({
+            "k": self.k,
+            "advice_columns": self.advice_columns,
+            "fixed_columns": self.fixed_columns,
+            "instance_columns": self.instance_columns,
+            "selectors": self.selectors,
+            "gates": self.gates,
+            "constraints": self.constraints,
+            "degree": self.degree,
+            "lookups": self.lookups,
+            "permutation_columns": self.permutation_columns,
+            "rows": self.rows,
+            "proof_size": self.proof.map(|p| p.bytes),
+            "marginal_proof_size": self.proof.map(|p| p.marginal_bytes),
+            "proof_commitments": self.proof.map(|p| p.commitments),
+            "proof_evaluations": self.proof.map(|p| p.evaluations),
+            "verifier_msm": self.verifier_msm,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum CostError {
+    // The circuit does not fit in any supported k
+    Size(SizeError),
+    // The layout pass failed
+    Layout(Error),
+}
+
+impl fmt::Display for CostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostError::Size(e) => write!(f, "{}", e),
+            CostError::Layout(e) => write!(f, "layout failed: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for CostError {}
+
+impl From<SizeError> for CostError {
+    fn from(e: SizeError) -> Self {
+        CostError::Size(e)
+    }
+}
+
+impl From<Error> for CostError {
+    fn from(e: Error) -> Self {
+        CostError::Layout(e)
+    }
+}
+
+// The numbers following every `key: ` in a debug string
+fn debug_numbers<'a>(debug: &'a str, key: &'a str) -> impl Iterator<Item = usize> + 'a {
+    debug.split(key).skip(1).filter_map(|rest| {
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    })
+}
+
+fn debug_number(debug: &str, key: &str) -> usize {
+    debug_numbers(debug, key).next().unwrap_or(0)
+}
+
+// Measures `circuit` laid out in a 2^k table
+pub fn measure<C: Circuit<Fp>>(circuit: &C, k: u32) -> Result<Cost, CostError> {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    C::configure(&mut meta);
+    let pinned = format!("{:?}", meta.pinned());
+
+    let permutation_columns = pinned
+        .split_once("permutation: Argument { columns: [")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map_or(0, |(columns, _)| columns.matches("Column {").count());
+
+    let gates = CircuitGates::collect::<Fp, C>().to_string();
+
+    let proof = (permutation_columns > 0).then(|| {
+        let cost = CircuitCost::<Eq, C>::measure(k as usize, circuit);
+        let size = cost.proof_size(1);
+        let contributions = format!("{:?}", size);
+        ProofCost {
+            commitments: debug_numbers(&contributions, "commitments: ").sum(),
+            evaluations: debug_numbers(&contributions, "evaluations: ").sum(),
+            bytes: size.into(),
+            marginal_bytes: cost.marginal_proof_size().into(),
+        }
+    });
+
+    Ok(Cost {
+        k,
+        advice_columns: debug_number(&pinned, "num_advice_columns: "),
+        fixed_columns: debug_number(&pinned, "num_fixed_columns: "),
+        instance_columns: debug_number(&pinned, "num_instance_columns: "),
+        selectors: debug_number(&pinned, "num_selectors: "),
+        gates: debug_number(&gates, "Total gates: "),
+        constraints: debug_number(&gates, "Total custom constraint polynomials: "),
+        degree: meta.degree(),
+        lookups: pinned.matches("input_expressions").count(),
+        permutation_columns,
+        rows: planner::rows_used::<Fp, C, C::FloorPlanner>(circuit)?,
+        proof,
+        verifier_msm: proof.map(|p| (1 << k) + p.commitments + p.evaluations),
+    })
+}
+
+struct Measure;
+
+impl CircuitVisitor for Measure {
+    type Output = Result<Cost, CostError>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, _: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        measure(&circuit, k)
+    }
+}
+
+// Every circuit in the crate, with `steps` additions (or the matching size) where it has a length
+pub fn all(steps: usize) -> Result<Vec<(&'static str, Cost)>, CostError> {
+    let inputs = Inputs { steps, ..Inputs::default() };
+    let mut reports = vec![];
+    for kind in CircuitKind::ALL {
+        reports.push((kind.id(), kind.visit(&inputs, Measure)??));
+    }
+
+    let wide = WideFiboCircuit::<Fp, 6> { steps, ..Default::default() };
+    reports.push(("wide<6>", measure(&wide, wide.k()?)?));
+
+    let doubling = DoublingCircuit::<Fp>::new(0, 16);
+    reports.push(("doubling", measure(&doubling, doubling.k()?)?));
+
+    let private_index = PrivateIndexCircuit::<Fp> { max: steps, ..Default::default() };
+    reports.push(("private-index", measure(&private_index, private_index.k()?)?));
+
+    let recurrence = Preset::Pell.circuit::<Fp>(steps);
+    reports.push(("recurrence", measure(&recurrence, recurrence.k()?)?));
+
+    let tribonacci = HigherOrderCircuit::<Fp, Tribonacci, 3>::new(steps);
+    reports.push(("tribonacci", measure(&tribonacci, tribonacci.k()?)?));
+
+    let batch = BatchCircuit::<Fp, 4> { seeds: vec![(Value::unknown(), Value::unknown()); 8], steps };
+    reports.push(("batch<4>", measure(&batch, batch.k()?)?));
+
+    let segment = SegmentCircuit::<Fp> { steps, ..Default::default() };
+    reports.push(("segment", measure(&segment, segment.k()?)?));
+
+    let integer = IntegerFiboCircuit::<4> { steps, ..Default::default() };
+    reports.push(("integer<4>", measure(&integer, integer.k::<Fp>()?)?));
+
+    let decomposition = DecompositionCircuit::<Fp, 256> { num_bits: 64, ..Default::default() };
+    reports.push(("decompose-64", measure(&decomposition, decomposition.k()?)?));
+
+    let interval = IntervalCircuit::<Fp, 256>::new(0, 18, 121, BoundKind::Constant, BoundKind::Instance)?;
+    reports.push(("interval", measure(&interval, interval.k()?)?));
+
+    // Too wide for the table, and the gate would be degree 1001
+    let auto = AutoRangeCircuit::<Fp, 1000, 256, 9>::default();
+    reports.push(("auto-1000", measure(&auto, auto.k()?)?));
+
+    Ok(reports)
+}
+
+pub fn to_json(reports: &[(&str, Cost)]) -> Json {
+    json!(reports
+        .iter()
+        .map(|(name, cost)| {
+            let mut json = cost.to_json();
+            json["circuit"] = json!(name);
+            json
+        })
+        .collect::<Vec<_>>())
+}
+
+pub fn table(reports: &[(&str, Cost)]) -> String {
+    let mut out = format!(
+        "{:>13} | {:>2} | {:>6} | {:>5} | {:>8} | {:>9} | {:>5} | {:>11} | {:>6} | {:>7} | {:>11} | {:>6} | {:>12} | {:>12}\n",
+        "circuit", "k", "advice", "fixed", "instance", "selectors", "gates", "constraints", "degree", "lookups",
+        "permutation", "rows", "proof bytes", "verifier msm"
+    );
+    for (name, cost) in reports {
+        let proof = cost.proof.map_or("-".to_string(), |p| p.bytes.to_string());
+        let verifier_msm = cost.verifier_msm.map_or("-".to_string(), |msm| msm.to_string());
+        out += &format!(
+            "{:>13} | {:>2} | {:>6} | {:>5} | {:>8} | {:>9} | {:>5} | {:>11} | {:>6} | {:>7} | {:>11} | {:>6} | {:>12} | {:>12}\n",
+            name,
+            cost.k,
+            cost.advice_columns,
+            cost.fixed_columns,
+            cost.instance_columns,
+            cost.selectors,
+            cost.gates,
+            cost.constraints,
+            cost.degree,
+            cost.lookups,
+            cost.permutation_columns,
+            cost.rows,
+            proof,
+            verifier_msm
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fibonacci::ThreeColumnFiboCircuit;
+
+    #[test]
+    fn test_cost_three_column_fibonacci() {
+        let circuit = ThreeColumnFiboCircuit::<Fp> { steps: 8, ..Default::default() };
+        let cost = measure(&circuit, 4).unwrap();
+
+        assert_eq!(
+            (cost.advice_columns, cost.fixed_columns, cost.instance_columns, cost.selectors),
+            (3, 0, 1, 1)
+        );
+        assert_eq!((cost.gates, cost.constraints, cost.degree), (1, 1, 3));
+        assert_eq!((cost.lookups, cost.permutation_columns, cost.rows), (0, 4, 8));
+
+        let proof = cost.proof.unwrap();
+        let expected: usize = CircuitCost::<Eq, _>::measure(4, &circuit).proof_size(1).into();
+        assert_eq!(proof.bytes, expected);
+        // 32 bytes per commitment and per evaluation
+        assert_eq!(proof.bytes, 32 * (proof.commitments + proof.evaluations));
+        assert_eq!(cost.verifier_msm, Some(16 + proof.commitments + proof.evaluations));
+
+        // A circuit with more columns at the same k costs the verifier more
+        let wide = measure(&WideFiboCircuit::<Fp, 6> { steps: 8, ..Default::default() }, 4).unwrap();
+        assert!(wide.verifier_msm.unwrap() > cost.verifier_msm.unwrap());
+    }
+
+    #[test]
+    fn test_cost_without_permutation() {
+        // The expression range check has no equality constraints, so no proof estimate
+        let circuit = crate::range_check::example1::MyCircuit::<Fp, 8>::default();
+        let cost = measure(&circuit, 4).unwrap();
+        assert_eq!(cost.permutation_columns, 0);
+        assert_eq!(cost.proof, None);
+        assert_eq!(cost.to_json()["proof_size"], Json::Null);
+        assert_eq!(cost.verifier_msm, None);
+        // v * (1 - v) * ... * (7 - v) under a selector
+        assert_eq!(cost.degree, 9);
+    }
+
+    #[test]
+    fn test_cost_all() {
+        let reports = all(8).unwrap();
+        assert_eq!(reports.len(), CircuitKind::ALL.len() + 11);
+
+        let (_, lookup) = reports.iter().find(|(name, _)| *name == "range-lookup").unwrap();
+        assert_eq!(lookup.lookups, 1);
+        assert_eq!(lookup.rows, 511);
+
+        let (_, integer) = reports.iter().find(|(name, _)| *name == "integer<4>").unwrap();
+        assert_eq!(integer.lookups, 4);
+
+        let (_, auto) = reports.iter().find(|(name, _)| *name == "auto-1000").unwrap();
+        assert_eq!(auto.degree, 6);
+
+        let table = table(&reports);
+        assert_eq!(table.lines().count(), reports.len() + 1);
+        assert_eq!(to_json(&reports).as_array().unwrap().len(), reports.len());
+    }
+}
@@ -0,0 +1,513 @@
+// Versioned on-disk envelope for shipping a proof together with everything needed to check it.
+//
+// Layout (all integers little-endian):
+//
+//   magic        | "H2EX"
+//   version      | u16
+//   circuit id   | u16 length + utf-8
+//   k            | u32
+//   field        | u16 length + utf-8
+//   transcript   | u16 length + utf-8
+//   halo2        | u16 length + utf-8
+//   params       | u64 length + `Params::write` bytes
+//   vk           | u64 length + pinned verifying-key bytes
+//   instances    | u32 column count, then per column u32 length + 32-byte field elements
+//   proof        | u64 length + proof bytes
+//   checksum     | 32-byte Blake2b of everything above
+//
+// halo2_proofs 0.2 cannot serialise a `VerifyingKey`, so the envelope carries its pinned
+// representation instead. This is the same data halo2 hashes into the transcript, so a
+// receiver regenerates the key from the params and the circuit and compares the two.
+// The params themselves are not trusted either: IPA has no setup, so they are only accepted
+// if they are byte for byte the params `Params::new(k)` generates.
+//
+// The pinned key is written out through its `Debug` output, which is not a stable format:
+// it can change with any halo2 release. The vk bytes are therefore only comparable between
+// builds against the same halo2 version, which the header records so that a mismatch is
+// reported as such rather than as a verifying key that does not match.
+
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use halo2_proofs::{
+    pasta::{group::ff::PrimeField, EqAffine, Fp},
+    plonk::VerifyingKey,
+    poly::commitment::Params,
+};
+
+use crate::{prove, sizing};
+
+pub const MAGIC: [u8; 4] = *b"H2EX";
+pub const VERSION: u16 = 2;
+pub const FIELD: &str = "pasta-fp";
+pub const TRANSCRIPT: &str = "blake2b-challenge255";
+// The halo2_proofs release the vk bytes come from; bump it together with the dependency
+pub const HALO2: &str = "halo2_proofs-0.2.0";
+
+const CHECKSUM_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    ChecksumMismatch,
+    Malformed(&'static str),
+    CircuitMismatch { expected: String, found: String },
+    KMismatch { expected: u32, found: u32 },
+    FieldMismatch { expected: String, found: String },
+    TranscriptMismatch { expected: String, found: String },
+    Halo2Mismatch { expected: String, found: String },
+    ParamsMismatch,
+    VerifyingKeyMismatch,
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::Io(e) => write!(f, "i/o error: {}", e),
+            EnvelopeError::BadMagic => write!(f, "not a proof envelope (bad magic bytes)"),
+            EnvelopeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported envelope version {} (expected {})", v, VERSION)
+            }
+            EnvelopeError::ChecksumMismatch => write!(f, "envelope checksum does not match its contents"),
+            EnvelopeError::Malformed(what) => write!(f, "malformed envelope: {}", what),
+            EnvelopeError::CircuitMismatch { expected, found } => write!(
+                f,
+                "envelope was produced for circuit `{}`, expected `{}`",
+                found, expected
+            ),
+            EnvelopeError::KMismatch { expected, found } => {
+                write!(f, "envelope was produced with k = {}, expected k = {}", found, expected)
+            }
+            EnvelopeError::FieldMismatch { expected, found } => {
+                write!(f, "envelope uses field `{}`, expected `{}`", found, expected)
+            }
+            EnvelopeError::TranscriptMismatch { expected, found } => {
+                write!(f, "envelope uses transcript `{}`, expected `{}`", found, expected)
+            }
+            EnvelopeError::Halo2Mismatch { expected, found } => write!(
+                f,
+                "envelope was produced with `{}`, whose verifying keys cannot be compared with `{}`",
+                found, expected
+            ),
+            EnvelopeError::ParamsMismatch => {
+                write!(f, "params in the envelope are not the params generated for its k")
+            }
+            EnvelopeError::VerifyingKeyMismatch => {
+                write!(f, "verifying key in the envelope does not match the circuit")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl From<io::Error> for EnvelopeError {
+    fn from(e: io::Error) -> Self {
+        EnvelopeError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub circuit_id: String,
+    pub k: u32,
+    pub field: String,
+    pub transcript: String,
+    pub halo2: String,
+}
+
+#[derive(Debug)]
+pub struct Envelope {
+    pub header: Header,
+    pub params: Params<EqAffine>,
+    pub vk: Vec<u8>,
+    pub instances: Vec<Vec<Fp>>,
+    pub proof: Vec<u8>,
+}
+
+// Bytes identifying a verifying key, stored in the envelope in place of the key itself
+pub fn vk_bytes(vk: &VerifyingKey<EqAffine>) -> Vec<u8> {
+    format!("{:?}", vk.pinned()).into_bytes()
+}
+
+impl Envelope {
+    pub fn new(
+        circuit_id: &str,
+        k: u32,
+        params: Params<EqAffine>,
+        vk: &VerifyingKey<EqAffine>,
+        instances: Vec<Vec<Fp>>,
+        proof: Vec<u8>,
+    ) -> Self {
+        Envelope {
+            header: Header {
+                circuit_id: circuit_id.to_string(),
+                k,
+                field: FIELD.to_string(),
+                transcript: TRANSCRIPT.to_string(),
+                halo2: HALO2.to_string(),
+            },
+            params,
+            vk: vk_bytes(vk),
+            instances,
+            proof,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut body = vec![];
+        body.extend_from_slice(&MAGIC);
+        body.extend_from_slice(&VERSION.to_le_bytes());
+        write_str(&mut body, &self.header.circuit_id);
+        body.extend_from_slice(&self.header.k.to_le_bytes());
+        write_str(&mut body, &self.header.field);
+        write_str(&mut body, &self.header.transcript);
+        write_str(&mut body, &self.header.halo2);
+
+        let mut params = vec![];
+        self.params.write(&mut params)?;
+        write_bytes(&mut body, &params);
+        write_bytes(&mut body, &self.vk);
+
+        body.extend_from_slice(&(self.instances.len() as u32).to_le_bytes());
+        for column in &self.instances {
+            body.extend_from_slice(&(column.len() as u32).to_le_bytes());
+            for value in column {
+                body.extend_from_slice(value.to_repr().as_ref());
+            }
+        }
+        write_bytes(&mut body, &self.proof);
+
+        let checksum = checksum(&body);
+        writer.write_all(&body)?;
+        writer.write_all(&checksum)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, EnvelopeError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+            return Err(EnvelopeError::BadMagic);
+        }
+        if bytes.len() < MAGIC.len() + 2 + CHECKSUM_LEN {
+            return Err(EnvelopeError::Malformed("truncated header"));
+        }
+        let (body, stored) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+
+        let mut cursor = Cursor { bytes: body, pos: MAGIC.len() };
+        // Check the version before the checksum so that a newer file is reported as such
+        let version = u16::from_le_bytes(cursor.array()?);
+        if version != VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        if checksum(body) != stored {
+            return Err(EnvelopeError::ChecksumMismatch);
+        }
+
+        let circuit_id = cursor.string()?;
+        let k = u32::from_le_bytes(cursor.array()?);
+        let field = cursor.string()?;
+        let transcript = cursor.string()?;
+        let halo2 = cursor.string()?;
+        let header = Header { circuit_id, k, field, transcript, halo2 };
+        if header.field != FIELD {
+            return Err(EnvelopeError::FieldMismatch { expected: FIELD.to_string(), found: header.field });
+        }
+        if header.transcript != TRANSCRIPT {
+            return Err(EnvelopeError::TranscriptMismatch {
+                expected: TRANSCRIPT.to_string(),
+                found: header.transcript,
+            });
+        }
+        if header.halo2 != HALO2 {
+            return Err(EnvelopeError::Halo2Mismatch { expected: HALO2.to_string(), found: header.halo2 });
+        }
+        // The params hold 2^k points, and Params::new stops at 2^31
+        if header.k > sizing::PARAMS_MAX_K {
+            return Err(EnvelopeError::Malformed("k exceeds the largest supported circuit size"));
+        }
+
+        let params_bytes = cursor.bytes()?;
+        // `Params::write` starts with k, which has to agree with the header
+        if params_bytes.len() < 4 {
+            return Err(EnvelopeError::Malformed("truncated params"));
+        }
+        let params_k = u32::from_le_bytes(params_bytes[..4].try_into().unwrap());
+        if params_k != header.k {
+            return Err(EnvelopeError::KMismatch { expected: header.k, found: params_k });
+        }
+        // IPA params are deterministic, so the shipped ones are only accepted if they are
+        // exactly the ones the receiver generates for k
+        let params = prove::params(header.k);
+        let mut expected = vec![];
+        params.write(&mut expected)?;
+        if params_bytes != expected {
+            return Err(EnvelopeError::ParamsMismatch);
+        }
+
+        let vk = cursor.bytes()?.to_vec();
+
+        let columns = u32::from_le_bytes(cursor.array()?);
+        let mut instances = vec![];
+        for _ in 0..columns {
+            let len = u32::from_le_bytes(cursor.array()?);
+            let column = (0..len)
+                .map(|_| {
+                    let repr = cursor.array()?;
+                    Option::from(Fp::from_repr(repr)).ok_or(EnvelopeError::Malformed("non-canonical field element"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            instances.push(column);
+        }
+
+        let proof = cursor.bytes()?.to_vec();
+        if cursor.pos != body.len() {
+            return Err(EnvelopeError::Malformed("trailing bytes after proof"));
+        }
+
+        Ok(Envelope { header, params, vk, instances, proof })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EnvelopeError> {
+        let mut file = fs::File::open(path)?;
+        Self::read(&mut file)
+    }
+
+    // Checks that the envelope was produced for the circuit and size the caller expects
+    pub fn check(&self, circuit_id: &str, k: u32) -> Result<(), EnvelopeError> {
+        if self.header.circuit_id != circuit_id {
+            return Err(EnvelopeError::CircuitMismatch {
+                expected: circuit_id.to_string(),
+                found: self.header.circuit_id.clone(),
+            });
+        }
+        if self.header.k != k {
+            return Err(EnvelopeError::KMismatch { expected: k, found: self.header.k });
+        }
+        Ok(())
+    }
+
+    // Checks that `vk`, regenerated by the receiver, is the key the proof was made for
+    pub fn check_vk(&self, vk: &VerifyingKey<EqAffine>) -> Result<(), EnvelopeError> {
+        if self.vk != vk_bytes(vk) {
+            return Err(EnvelopeError::VerifyingKeyMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(CHECKSUM_LEN)
+        .personal(b"halo2-envelope")
+        .hash(bytes);
+    hash.as_bytes().try_into().unwrap()
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EnvelopeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(EnvelopeError::Malformed("unexpected end of envelope"))?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], EnvelopeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn string(&mut self) -> Result<String, EnvelopeError> {
+        let len = u16::from_le_bytes(self.array()?) as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| EnvelopeError::Malformed("invalid utf-8 string"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], EnvelopeError> {
+        let len = u64::from_le_bytes(self.array()?);
+        let len = usize::try_from(len).map_err(|_| EnvelopeError::Malformed("section too large"))?;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{circuit::Value, plonk::{keygen_vk, Circuit}};
+
+    use super::*;
+    use crate::{
+        fibonacci::ThreeColumnFiboCircuit,
+        prove::{self, Verification},
+    };
+
+    fn fibonacci_envelope() -> Envelope {
+        let circuit = ThreeColumnFiboCircuit {
+            a: Value::known(Fp::from(1)),
+            b: Value::known(Fp::from(1)),
+            steps: 8,
+        };
+        let k = circuit.k().unwrap();
+        let instances = vec![vec![Fp::from(1), Fp::from(1), Fp::from(55)]];
+
+        let params = prove::params(k);
+        let pk = prove::keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove::prove(&params, &pk, circuit, &instances).unwrap();
+
+        Envelope::new("fib3col", k, params, pk.get_vk(), instances, proof)
+    }
+
+    fn to_bytes(envelope: &Envelope) -> Vec<u8> {
+        let mut bytes = vec![];
+        envelope.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let envelope = fibonacci_envelope();
+        let read = Envelope::read(&mut to_bytes(&envelope).as_slice()).unwrap();
+
+        assert_eq!(read.header, envelope.header);
+        assert_eq!(read.instances, envelope.instances);
+        assert_eq!(read.proof, envelope.proof);
+        read.check("fib3col", 4).unwrap();
+
+        // The receiver rebuilds the verifying key from the shipped params and the circuit
+        let circuit = ThreeColumnFiboCircuit::<Fp> { steps: 8, ..Default::default() };
+        let vk = keygen_vk(&read.params, &circuit).unwrap();
+        read.check_vk(&vk).unwrap();
+        assert!(prove::verify(&read.params, &vk, &read.proof, &read.instances).is_valid());
+    }
+
+    #[test]
+    fn test_envelope_rejects_other_circuit() {
+        let read = Envelope::read(&mut to_bytes(&fibonacci_envelope()).as_slice()).unwrap();
+
+        assert!(matches!(read.check("fib1col", 4), Err(EnvelopeError::CircuitMismatch { .. })));
+        assert!(matches!(
+            read.check("fib3col", 5),
+            Err(EnvelopeError::KMismatch { expected: 5, found: 4 })
+        ));
+
+        // A longer sequence has a different verifying key
+        let circuit = ThreeColumnFiboCircuit::<Fp> { steps: 9, ..Default::default() };
+        let vk = keygen_vk(&read.params, &circuit).unwrap();
+        assert!(matches!(read.check_vk(&vk), Err(EnvelopeError::VerifyingKeyMismatch)));
+    }
+
+    #[test]
+    fn test_envelope_integrity() {
+        let bytes = to_bytes(&fibonacci_envelope());
+
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - CHECKSUM_LEN - 1;
+        corrupted[last] ^= 1;
+        assert!(matches!(Envelope::read(&mut corrupted.as_slice()), Err(EnvelopeError::ChecksumMismatch)));
+
+        let mut newer = bytes.clone();
+        newer[4] = VERSION as u8 + 1;
+        assert!(matches!(Envelope::read(&mut newer.as_slice()), Err(EnvelopeError::UnsupportedVersion(3))));
+
+        assert!(matches!(Envelope::read(&mut &b"nope"[..]), Err(EnvelopeError::BadMagic)));
+        assert!(matches!(
+            Envelope::read(&mut &bytes[..bytes.len() / 2]),
+            Err(EnvelopeError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_envelope_file() {
+        let path = std::env::temp_dir().join(format!("halo2-envelope-{}.bin", std::process::id()));
+        let envelope = fibonacci_envelope();
+        envelope.save(&path).unwrap();
+
+        let read = Envelope::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let vk = keygen_vk(&read.params, &ThreeColumnFiboCircuit::<Fp> { steps: 8, ..Default::default() }).unwrap();
+        assert!(matches!(
+            prove::verify(&read.params, &vk, &read.proof, &read.instances),
+            Verification::Valid
+        ));
+    }
+
+    // Patches the envelope bytes at `pos` and recomputes the checksum, as a crafted file would
+    fn patch(bytes: &[u8], pos: usize, with: &[u8]) -> Vec<u8> {
+        let mut body = bytes[..bytes.len() - CHECKSUM_LEN].to_vec();
+        body[pos..pos + with.len()].copy_from_slice(with);
+        let checksum = checksum(&body);
+        body.extend_from_slice(&checksum);
+        body
+    }
+
+    #[test]
+    fn test_envelope_rejects_large_k() {
+        let bytes = to_bytes(&fibonacci_envelope());
+
+        // k in the header, and again at the start of the params
+        let k_pos = MAGIC.len() + 2 + 2 + "fib3col".len();
+        let params_k_pos = k_pos + 4 + [FIELD, TRANSCRIPT, HALO2].iter().map(|s| 2 + s.len()).sum::<usize>() + 8;
+        assert_eq!(bytes[k_pos..k_pos + 4], 4u32.to_le_bytes());
+        assert_eq!(bytes[params_k_pos..params_k_pos + 4], 4u32.to_le_bytes());
+
+        for k in [Fp::S, Fp::S + 1, 64, u32::MAX] {
+            let crafted = patch(&patch(&bytes, k_pos, &k.to_le_bytes()), params_k_pos, &k.to_le_bytes());
+            assert!(matches!(Envelope::read(&mut crafted.as_slice()), Err(EnvelopeError::Malformed(_))));
+        }
+    }
+
+    #[test]
+    fn test_envelope_rejects_other_halo2() {
+        let bytes = to_bytes(&fibonacci_envelope());
+        let halo2_pos = MAGIC.len() + 2 + 2 + "fib3col".len() + 4 + 2 + FIELD.len() + 2 + TRANSCRIPT.len() + 2;
+        assert_eq!(&bytes[halo2_pos..halo2_pos + HALO2.len()], HALO2.as_bytes());
+
+        let other = HALO2.replace("0.2.0", "0.3.0");
+        let crafted = patch(&bytes, halo2_pos, other.as_bytes());
+        match Envelope::read(&mut crafted.as_slice()) {
+            Err(EnvelopeError::Halo2Mismatch { expected, found }) => assert_eq!((expected, found), (HALO2.to_string(), other)),
+            other => panic!("expected a halo2 mismatch, got {:?}", other.map(|envelope| envelope.header)),
+        }
+    }
+    #[test]
+    fn test_envelope_rejects_other_params() {
+        let bytes = to_bytes(&fibonacci_envelope());
+
+        // A byte of the first generator, past the k that starts the params
+        let params_pos = MAGIC.len() + 2 + 2 + "fib3col".len() + 4
+            + [FIELD, TRANSCRIPT, HALO2].iter().map(|s| 2 + s.len()).sum::<usize>()
+            + 8;
+        let byte = [bytes[params_pos + 4] ^ 1];
+
+        let crafted = patch(&bytes, params_pos + 4, &byte);
+        assert!(matches!(Envelope::read(&mut crafted.as_slice()), Err(EnvelopeError::ParamsMismatch)));
+    }
+}
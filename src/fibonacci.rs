@@ -1,2 +1,82 @@
-mod example1;
-mod example2;
\ No newline at end of file
+use halo2_proofs::arithmetic::FieldExt;
+
+use crate::instance::InstanceColumn;
+
+pub mod example1;
+pub mod example2;
+pub mod doubling;
+pub mod private_index;
+pub mod recurrence;
+pub mod higher_order;
+pub mod batch;
+pub mod wide;
+pub mod segment;
+pub mod integer;
+
+// The two chips share their type names inside their modules, so they are re-exported
+// under names that describe the layout
+pub use example1::{
+    FiboChip as ThreeColumnFiboChip, FiboConfig as ThreeColumnFiboConfig,
+    MyCircuit as ThreeColumnFiboCircuit,
+};
+pub use example2::{
+    FiboChip as SingleColumnFiboChip, FiboConfig as SingleColumnFiboConfig,
+    MyCircuit as SingleColumnFiboCircuit,
+};
+
+// Public inputs of the circuits proving out = F(steps + 1) for the sequence F(0) = a, F(1) = b.
+// Synthesis takes the instance rows from here, so the prover, the verifier and the circuit
+// agree on the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<F> {
+    pub a: F,
+    pub b: F,
+    pub out: F,
+}
+
+impl<F: FieldExt> PublicInputs<F> {
+    pub const A_ROW: usize = 0;
+    pub const B_ROW: usize = 1;
+    pub const OUT_ROW: usize = 2;
+
+    // Computes the output natively
+    pub fn new(a: F, b: F, steps: usize) -> Self {
+        let out = (0..steps).fold((a, b), |(a, b), _| (b, a + b)).1;
+        Self { a, b, out }
+    }
+}
+
+impl<F: FieldExt> InstanceColumn<F> for PublicInputs<F> {
+    const LEN: usize = 3;
+
+    fn column(&self) -> Vec<F> {
+        vec![self.a, self.b, self.out]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{circuit::Value, dev::MockProver, pasta::Fp};
+
+    use super::*;
+
+    #[test]
+    fn test_public_inputs_layout() {
+        let (a, b) = (Fp::from(2), Fp::from(3));
+        let public_inputs = PublicInputs::new(a, b, 8);
+        assert_eq!(public_inputs.column(), [2, 3, 144].map(Fp::from));
+
+        // Both layouts read the same rows, and swapping the seeds is caught
+        let swapped = PublicInputs { a: b, b: a, ..public_inputs };
+
+        let three_columns = ThreeColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps: 8 };
+        let k = three_columns.k().unwrap();
+        MockProver::run(k, &three_columns, public_inputs.instances()).unwrap().assert_satisfied();
+        assert!(MockProver::run(k, &three_columns, swapped.instances()).unwrap().verify().is_err());
+
+        let single_column = SingleColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps: 8 };
+        let k = single_column.k().unwrap();
+        MockProver::run(k, &single_column, public_inputs.instances()).unwrap().assert_satisfied();
+        assert!(MockProver::run(k, &single_column, swapped.instances()).unwrap().verify().is_err());
+    }
+}
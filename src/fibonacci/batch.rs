@@ -0,0 +1,204 @@
+/*
+Batched three column fibonacci circuit
+
+Proves many independent (a, b) -> out sequences in one proof. The sequences are spread
+round-robin over GROUPS copies of the three column chip; within a group they are stacked
+one after the other, and the floor planner places groups side by side since they share
+no columns.
+*/
+
+use halo2_proofs::{
+    arithmetic::FieldExt, circuit::*, plonk::*
+};
+
+use super::{
+    example1::{FiboChip, FiboConfig},
+    PublicInputs,
+};
+use crate::{
+    instance::InstanceColumn,
+    sizing::{self, SizeError},
+    witness::FibonacciWitness,
+};
+
+// Example, 5 sequences of 2 steps over 2 groups
+// Row | group 0      | group 1      | i
+//  0  | seq 0 row 0  | seq 1 row 0  | a(0)
+//  1  | seq 0 row 1  | seq 1 row 1  | b(0)
+//  2  | seq 2 row 0  | seq 3 row 0  | out(0)
+//  3  | seq 2 row 1  | seq 3 row 1  | a(1)
+//  4  | seq 4 row 0  |              | ...
+//  5  | seq 4 row 1  |              |
+//  ...                                out(4) at row 14
+//
+// Sequence i exposes a, b and out at instance rows 3i, 3i + 1 and 3i + 2.
+
+#[derive(Debug, Clone)]
+pub struct BatchConfig<const GROUPS: usize> {
+    pub groups: [FiboConfig; GROUPS],
+}
+
+// Proves out(i) = F(steps + 1) from (a(i), b(i)) for every pair of seeds
+pub struct BatchCircuit<F, const GROUPS: usize> {
+    pub seeds: Vec<(Value<F>, Value<F>)>,
+    // Number of additions of every sequence
+    pub steps: usize,
+}
+
+impl<F: FieldExt, const GROUPS: usize> BatchCircuit<F, GROUPS> {
+    pub fn new(seeds: &[(F, F)], steps: usize) -> Self {
+        Self {
+            seeds: seeds.iter().map(|(a, b)| (Value::known(*a), Value::known(*b))).collect(),
+            steps,
+        }
+    }
+
+    // The tallest group holds ceil(B / GROUPS) sequences; the instance column needs 3B rows
+    pub fn rows(&self) -> usize {
+        let per_group = self.seeds.len().div_ceil(GROUPS);
+        (per_group * self.steps).max(3 * self.seeds.len())
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 || self.seeds.is_empty() {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+
+    // Public inputs for the given seeds: a, b, out for every sequence
+    pub fn instances(seeds: &[(F, F)], steps: usize) -> Vec<Vec<F>> {
+        let column = seeds
+            .iter()
+            .flat_map(|&(a, b)| PublicInputs::new(a, b, steps).column())
+            .collect();
+        vec![column]
+    }
+}
+
+impl<F: FieldExt, const GROUPS: usize> Circuit<F> for BatchCircuit<F, GROUPS> {
+    type Config = BatchConfig<GROUPS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            seeds: vec![(Value::unknown(), Value::unknown()); self.seeds.len()],
+            steps: self.steps,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        // Every group writes its public values to the same instance column
+        let instance = meta.instance_column();
+        let groups = [(); GROUPS].map(|_| {
+            let advice = [(); 3].map(|_| meta.advice_column());
+            FiboChip::configure(meta, advice, instance)
+        });
+        BatchConfig { groups }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let chips = config.groups.map(FiboChip::construct);
+
+        for (i, (a, b)) in self.seeds.iter().enumerate() {
+            let chip = &chips[i % GROUPS];
+            let mut layouter = layouter.namespace(|| format!("sequence {}", i));
+            // Sequence i takes its own block of rows in the instance column
+            let offset = i * PublicInputs::<F>::LEN;
+            let values = a
+                .zip(*b)
+                .map(|(a, b)| FibonacciWitness::generate(a, b, self.steps).values)
+                .transpose_vec(self.steps + 2);
+
+            let (prev_a, mut prev_b, mut prev_c) = chip.assign_first_row(
+                layouter.namespace(|| "first row"),
+                values[0], values[1], values[2],
+            )?;
+
+            chip.expose_public(layouter.namespace(|| "private a"), &prev_a, offset + PublicInputs::<F>::A_ROW)?;
+            chip.expose_public(layouter.namespace(|| "private b"), &prev_b, offset + PublicInputs::<F>::B_ROW)?;
+
+            for step in 1..self.steps {
+                let c_cell = chip.assign_row(
+                    layouter.namespace(|| "next row"),
+                    &prev_b,
+                    &prev_c,
+                    values[step + 2],
+                )?;
+                prev_b = prev_c;
+                prev_c = c_cell;
+            }
+
+            chip.expose_public(layouter.namespace(|| "output"), &prev_c, offset + PublicInputs::<F>::OUT_ROW)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::prove;
+
+    fn seeds(count: u64) -> Vec<(Fp, Fp)> {
+        (0..count).map(|i| (Fp::from(i), Fp::from(i + 1))).collect()
+    }
+
+    #[test]
+    fn test_batch_single_group() {
+        let seeds = seeds(5);
+        let circuit = BatchCircuit::<Fp, 1>::new(&seeds, 8);
+        let instances = BatchCircuit::<Fp, 1>::instances(&seeds, 8);
+        // 0, 1, 1, 2, 3, 5, 8, 13, 21, 34
+        assert_eq!(instances[0][2], Fp::from(34));
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, instances).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_batch_groups_share_rows() {
+        let seeds = seeds(5);
+        let one_group = BatchCircuit::<Fp, 1>::new(&seeds, 8);
+        let four_groups = BatchCircuit::<Fp, 4>::new(&seeds, 8);
+        assert_eq!(one_group.rows(), 40);
+        assert_eq!(four_groups.rows(), 16);
+        assert_eq!(one_group.k(), Ok(6));
+        assert_eq!(four_groups.k(), Ok(5));
+
+        let instances = BatchCircuit::<Fp, 4>::instances(&seeds, 8);
+        let prover = MockProver::run(four_groups.k().unwrap(), &four_groups, instances).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_batch_wrong_output() {
+        let seeds = seeds(5);
+        let circuit = BatchCircuit::<Fp, 2>::new(&seeds, 8);
+        let mut instances = BatchCircuit::<Fp, 2>::instances(&seeds, 8);
+        // Output of sequence 3
+        instances[0][11] += Fp::one();
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, instances).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_batch_proof() {
+        let seeds = seeds(6);
+        let circuit = BatchCircuit::<Fp, 3>::new(&seeds, 8);
+        let instances = BatchCircuit::<Fp, 3>::instances(&seeds, 8);
+
+        let params = prove::params(circuit.k().unwrap());
+        let pk = prove::keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove::prove(&params, &pk, circuit, &instances).unwrap();
+        assert!(prove::verify(&params, pk.get_vk(), &proof, &instances).is_valid());
+    }
+}
@@ -0,0 +1,348 @@
+/*
+Fast-doubling fibonacci circuit
+
+Proves F(n) for the standard sequence F(0) = 0, F(1) = 1 in O(log n) rows using
+
+    F(2m)     = F(m) * (2 * F(m + 1) - F(m))
+    F(2m + 1) = F(m)^2 + F(m + 1)^2
+*/
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
+};
+
+use crate::{
+    instance::InstanceColumn,
+    sizing::{self, SizeError},
+};
+
+// Example, n = 11 = 0b1011 (bits are consumed from the most significant one)
+// Row |  x   |  y   | bit | acc | s
+//  0  |   0  |   1  |  1  |  0  | 1      m = 0
+//  1  |   1  |   1  |  0  |  1  | 1      m = 1
+//  2  |   1  |   2  |  1  |  2  | 1      m = 2
+//  3  |   2  |   3  |  1  |  5  | 1      m = 5
+//  4  |  89  | 144  |     | 11  |        m = 11
+//
+// Each row holds x = F(m), y = F(m + 1) and acc = m. The gate doubles m and adds the bit:
+//     bit = 0:  (x', y') = (F(2m), F(2m + 1))
+//     bit = 1:  (x', y') = (F(2m + 1), F(2m) + F(2m + 1))
+//     acc' = 2 * acc + bit
+
+#[derive(Debug, Clone)]
+pub struct DoublingConfig {
+    pub x: Column<Advice>,
+    pub y: Column<Advice>,
+    pub bit: Column<Advice>,
+    pub acc: Column<Advice>,
+    pub constant: Column<Fixed>,
+    pub selector: Selector,
+    pub instance: Column<Instance>,
+}
+
+pub struct DoublingChip<F: FieldExt> {
+    config: DoublingConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> DoublingChip<F> {
+    pub fn construct(config: DoublingConfig) -> Self {
+        Self { config, _marker: PhantomData }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 4],
+        constant: Column<Fixed>,
+        instance: Column<Instance>,
+    ) -> DoublingConfig {
+        let [x, y, bit, acc] = advice;
+        let selector = meta.selector();
+
+        // The first row is fixed to (F(0), F(1), 0) through the constants column,
+        // and the last row is compared against the instance column
+        meta.enable_constant(constant);
+        meta.enable_equality(x);
+        meta.enable_equality(y);
+        meta.enable_equality(acc);
+        meta.enable_equality(instance);
+
+        meta.create_gate("double", |meta| {
+            let s = meta.query_selector(selector);
+            let x = meta.query_advice(x, Rotation::cur());
+            let y = meta.query_advice(y, Rotation::cur());
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            let x_next = meta.query_advice(advice[0], Rotation::next());
+            let y_next = meta.query_advice(advice[1], Rotation::next());
+            let acc_next = meta.query_advice(advice[3], Rotation::next());
+
+            let one = Expression::Constant(F::one());
+            let two = Expression::Constant(F::from(2));
+
+            // F(2m) and F(2m + 1)
+            let even = x.clone() * (two.clone() * y.clone() - x.clone());
+            let odd = x.clone() * x + y.clone() * y;
+
+            Constraints::with_selector(
+                s,
+                [
+                    ("bit is boolean", bit.clone() * (one - bit.clone())),
+                    ("x", x_next - (even.clone() + bit.clone() * (odd.clone() - even.clone()))),
+                    ("y", y_next - (odd + bit.clone() * even)),
+                    ("acc", acc_next - (two * acc + bit)),
+                ],
+            )
+        });
+
+        DoublingConfig {
+            x,
+            y,
+            bit,
+            acc,
+            constant,
+            selector,
+            instance,
+        }
+    }
+
+    // Lays out the whole ladder in one region and returns the cells holding n and F(n)
+    #[allow(clippy::type_complexity)]
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: &[Value<bool>],
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>), Error> {
+        layouter.assign_region(
+            || "doubling ladder",
+            |mut region| {
+                let mut x = region.assign_advice_from_constant(|| "F(0)", self.config.x, 0, F::zero())?;
+                let mut y = region.assign_advice_from_constant(|| "F(1)", self.config.y, 0, F::one())?;
+                let mut acc = region.assign_advice_from_constant(|| "m = 0", self.config.acc, 0, F::zero())?;
+
+                for (row, bit) in bits.iter().enumerate() {
+                    self.config.selector.enable(&mut region, row)?;
+                    let bit_val = bit.map(|b| F::from(b as u64));
+                    region.assign_advice(|| "bit", self.config.bit, row, || bit_val)?;
+
+                    let next = x.value().zip(y.value()).zip(*bit).map(|((x, y), bit)| {
+                        let even = *x * (y.double() - x);
+                        let odd = x.square() + y.square();
+                        if bit {
+                            (odd, even + odd)
+                        } else {
+                            (even, odd)
+                        }
+                    });
+                    let acc_val = acc.value().map(|acc| acc.double()) + bit_val;
+
+                    x = region.assign_advice(|| "x", self.config.x, row + 1, || next.map(|n| n.0))?;
+                    y = region.assign_advice(|| "y", self.config.y, row + 1, || next.map(|n| n.1))?;
+                    acc = region.assign_advice(|| "acc", self.config.acc, row + 1, || acc_val)?;
+                }
+
+                Ok((acc, x))
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: &AssignedCell<F, F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.config.instance, row)
+    }
+}
+
+// Public inputs of the doubling circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<F> {
+    pub n: F,
+    pub out: F,
+}
+
+impl<F: FieldExt> PublicInputs<F> {
+    pub const N_ROW: usize = 0;
+    pub const OUT_ROW: usize = 1;
+
+    // Computes F(n) natively with the same doubling formulas as the circuit
+    pub fn new(n: u64) -> Self {
+        let (out, _) = (0..u64::BITS).rev().fold((F::zero(), F::one()), |(x, y), i| {
+            let even = x * (y.double() - x);
+            let odd = x.square() + y.square();
+            if (n >> i) & 1 == 1 {
+                (odd, even + odd)
+            } else {
+                (even, odd)
+            }
+        });
+        Self { n: F::from(n), out }
+    }
+}
+
+impl<F: FieldExt> InstanceColumn<F> for PublicInputs<F> {
+    const LEN: usize = 2;
+
+    fn column(&self) -> Vec<F> {
+        vec![self.n, self.out]
+    }
+}
+
+// Proves F(n) = out with the public inputs [n, out].
+// n is decomposed into `num_bits` bits, so any n < 2^num_bits can be proven with the same keys.
+#[derive(Default)]
+pub struct DoublingCircuit<F> {
+    pub n: Value<u64>,
+    pub num_bits: usize,
+    pub _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> DoublingCircuit<F> {
+    pub fn new(n: u64, num_bits: usize) -> Self {
+        Self { n: Value::known(n), num_bits, _marker: PhantomData }
+    }
+
+    // One row per bit, plus the final state
+    pub fn rows(&self) -> usize {
+        self.num_bits + 1
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.num_bits == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        // n is a u64, and this also keeps acc far below the field modulus
+        if self.num_bits > 64 {
+            return Err(SizeError::TooManyBits { bits: self.num_bits, max: 64 });
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+
+    // Bits of n from the most significant one
+    fn bits(&self) -> Vec<Value<bool>> {
+        (0..self.num_bits)
+            .rev()
+            .map(|i| self.n.map(|n| (n >> i) & 1 == 1))
+            .collect()
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for DoublingCircuit<F> {
+    type Config = DoublingConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            n: Value::unknown(),
+            num_bits: self.num_bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); 4].map(|_| meta.advice_column());
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+        DoublingChip::configure(meta, advice, constant, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.num_bits > 64 {
+            return Err(Error::Synthesis);
+        }
+        let chip = DoublingChip::construct(config);
+
+        let (n_cell, out_cell) = chip.assign(layouter.namespace(|| "ladder"), &self.bits())?;
+
+        chip.expose_public(layouter.namespace(|| "n"), &n_cell, PublicInputs::<F>::N_ROW)?;
+        chip.expose_public(layouter.namespace(|| "F(n)"), &out_cell, PublicInputs::<F>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::fibonacci::{SingleColumnFiboCircuit, ThreeColumnFiboCircuit};
+
+    fn fibonacci(n: u64) -> Fp {
+        (0..n).fold((Fp::zero(), Fp::one()), |(a, b), _| (b, a + b)).0
+    }
+
+    #[test]
+    fn test_doubling() {
+        let circuit = DoublingCircuit::<Fp>::new(11, 4);
+        let k = circuit.k().unwrap();
+
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(11), Fp::from(89)]]).unwrap();
+        prover.assert_satisfied();
+
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(11), Fp::from(90)]]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The index is public too
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(10), Fp::from(89)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_doubling_matches_linear_chips() {
+        let num_bits = 5;
+        for n in 2..32u64 {
+            let out = fibonacci(n);
+
+            let doubling = DoublingCircuit::<Fp>::new(n, num_bits);
+            let prover = MockProver::run(doubling.k().unwrap(), &doubling, vec![vec![Fp::from(n), out]]).unwrap();
+            prover.assert_satisfied();
+
+            // F(n) is reached after n - 1 additions from F(0), F(1)
+            let instances = crate::fibonacci::PublicInputs { a: Fp::zero(), b: Fp::one(), out }.instances();
+            let three_columns = ThreeColumnFiboCircuit {
+                a: Value::known(Fp::zero()),
+                b: Value::known(Fp::one()),
+                steps: n as usize - 1,
+            };
+            let prover = MockProver::run(three_columns.k().unwrap(), &three_columns, instances.clone()).unwrap();
+            prover.assert_satisfied();
+
+            let single_column = SingleColumnFiboCircuit {
+                a: Value::known(Fp::zero()),
+                b: Value::known(Fp::one()),
+                steps: n as usize - 1,
+            };
+            let prover = MockProver::run(single_column.k().unwrap(), &single_column, instances).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_doubling_small_indices() {
+        for n in 0..2u64 {
+            let circuit = DoublingCircuit::<Fp>::new(n, 3);
+            let prover = MockProver::run(circuit.k().unwrap(), &circuit, PublicInputs::new(n).instances()).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_doubling_large_index() {
+        // F(100000) in 17 rows instead of 100000
+        let n = 100_000;
+        let circuit = DoublingCircuit::<Fp>::new(n, 17);
+        let k = circuit.k().unwrap();
+        assert_eq!(k, 5);
+
+        let public_inputs = PublicInputs::new(n);
+        assert_eq!(public_inputs.out, fibonacci(n));
+
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+    }
+}
@@ -5,7 +5,13 @@ Basic three column fibonacci circuit
 use std::marker::PhantomData;
 
 use halo2_proofs::{
-    arithmetic::FieldExt, circuit::*, dev::MockProver, pasta::Fp, plonk::*, poly::Rotation
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
+};
+
+use super::PublicInputs;
+use crate::{
+    sizing::{self, SizeError},
+    witness::FibonacciWitness,
 };
 
 // Example
@@ -17,7 +23,8 @@ use halo2_proofs::{
 //  4  |  5 |  8 | 13 | 1 |
 // ...
 
-struct ACell<F: FieldExt>(AssignedCell<F, F>);
+#[derive(Debug, Clone)]
+pub struct ACell<F: FieldExt>(pub AssignedCell<F, F>);
 
 // Defines the configuration of the columns
 // Advice columns hold the private inputs and other witnesses - vary over each proof
@@ -25,32 +32,32 @@ struct ACell<F: FieldExt>(AssignedCell<F, F>);
 // Fixed columns hold constants and lookup tables - circuit configuration
 // Selector columns hold control gates (binary constants) - circuit configuration
 #[derive(Debug, Clone)]
-struct FiboConfig {
+pub struct FiboConfig {
     pub advice: [Column<Advice>; 3],
     pub selector: Selector,
     pub instance: Column<Instance>
 }
 
 // The chip struct configures the constraints in the circuit and provides assignment functions
-struct FiboChip<F: FieldExt> {
+pub struct FiboChip<F: FieldExt> {
     config: FiboConfig,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt> FiboChip<F> {
     // Default constructor
-    fn construct(config: FiboConfig) -> Self {
+    pub fn construct(config: FiboConfig) -> Self {
         Self { config, _marker: PhantomData}
     }
 
     // This function will create columns and define the custom gates and enable the permutation check
-    // and return a config with all the gates 
+    // and return a config with all the gates
     // There are two different kinds of selectors:
     //  - Selector - halo2 BE will apply optimizations to the selectors and combine them while preserving which gate gets turned on where
     //  - Complex Selector - used in lookup arguments, selector has to always be binary thus cannot apply selector combining optimizations
-    fn configure(
-        meta: &mut ConstraintSystem<F>, 
-        advice: [Column<Advice>; 3], 
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 3],
         instance: Column<Instance>
     ) -> FiboConfig {
         // We pass them in because they can be shared accross different configs
@@ -74,7 +81,7 @@ impl<F: FieldExt> FiboChip<F> {
             //   a      b        c       s
             //
             let s = meta.query_selector(selector);
-            let a = meta.query_advice(col_a, Rotation::cur()); // curr means we are using the field from the same row as the selector 
+            let a = meta.query_advice(col_a, Rotation::cur()); // curr means we are using the field from the same row as the selector
             let b = meta.query_advice(col_b, Rotation::cur());
             let c = meta.query_advice(col_c, Rotation::cur());
             // return the constraint
@@ -90,35 +97,36 @@ impl<F: FieldExt> FiboChip<F> {
     }
 
     // These assign functions are to be called by the synthesizer, and will be used to assign values to the columns (the witness)
+    // The values themselves are computed beforehand (see `witness::FibonacciWitness`), the chip only places them
     // The layouter will collect all the region definitions and compress it vertically (i.e. squeeze up/down)
     // but not horizontally (i.e. will not squeeze left/right, at least right now)
-    fn assign_first_row(&self, mut layouter: impl Layouter<F>, a: Option<F>, b: Option<F>) -> Result<(ACell<F>, ACell<F>, ACell<F>), Error>{
+    #[allow(clippy::type_complexity)]
+    pub fn assign_first_row(&self, mut layouter: impl Layouter<F>, a: Value<F>, b: Value<F>, c: Value<F>) -> Result<(ACell<F>, ACell<F>, ACell<F>), Error>{
         layouter.assign_region(
-            || "first_row", 
+            || "first_row",
             |mut region| {
                 self.config.selector.enable(&mut region, 0)?;
 
                 let a_cell = region.assign_advice(
-                    || "a", 
-                    self.config.advice[0], 
-                    0, 
-                    || a.ok_or(Error::Synthesis),
+                    || "a",
+                    self.config.advice[0],
+                    0,
+                    || a,
                 ).map(ACell)?;
 
                 let b_cell = region.assign_advice(
-                    || "b", 
-                    self.config.advice[1], 
-                    0, 
-                    || b.ok_or(Error::Synthesis),
+                    || "b",
+                    self.config.advice[1],
+                    0,
+                    || b,
                 ).map(ACell)?;
 
-                let c_val = a.and_then(|a| b.map(|b| a + b));
-
+                // Value<F> propagates unknown witnesses (e.g. during keygen) instead of failing
                 let c_cell = region.assign_advice(
-                    || "c", 
-                    self.config.advice[2], 
-                    0, 
-                    || c_val.ok_or(Error::Synthesis),
+                    || "c",
+                    self.config.advice[2],
+                    0,
+                    || c,
                 ).map(ACell)?;
 
                Ok((a_cell, b_cell, c_cell))
@@ -126,7 +134,7 @@ impl<F: FieldExt> FiboChip<F> {
     }
 
     // This will be repeatedly called. Note that each time it makes a new region, comprised of a, b, c, s that happen to all be in the same row
-    fn assign_row(&self, mut layouter: impl Layouter<F>, prev_b: &ACell<F>, prev_c: &ACell<F>) -> Result<ACell<F>, Error> {
+    pub fn assign_row(&self, mut layouter: impl Layouter<F>, prev_b: &ACell<F>, prev_c: &ACell<F>, c: Value<F>) -> Result<ACell<F>, Error> {
         layouter.assign_region(
             || "next row",
             |mut region| {
@@ -139,17 +147,11 @@ impl<F: FieldExt> FiboChip<F> {
                 prev_b.0.copy_advice(|| "a", &mut region, self.config.advice[0], 0)?;
                 prev_c.0.copy_advice(|| "b", &mut region, self.config.advice[1], 0)?;
 
-                let c_val = prev_b.0.value().and_then(
-                    |b|  {
-                        prev_c.0.value().map(|c| *b + *c)
-                    }
-                );
-                
                 let c_cell = region.assign_advice(
-                    || "c", 
-                    self.config.advice[2], 
-                    0, 
-                    || c_val.ok_or(Error::Synthesis)
+                    || "c",
+                    self.config.advice[2],
+                    0,
+                    || c
                 ).map(ACell)?;
 
                 Ok(c_cell)
@@ -158,7 +160,7 @@ impl<F: FieldExt> FiboChip<F> {
     }
 
     pub fn expose_public(
-        &self, 
+        &self,
         mut layouter: impl Layouter<F>,
         cell: &ACell<F>,
         row: usize,         // Absolute index inside the instance column
@@ -167,10 +169,29 @@ impl<F: FieldExt> FiboChip<F> {
     }
 }
 
+// The circuit proves out = F(steps + 1) for the sequence F(0) = a, F(1) = b,
+// e.g. steps = 8 gives F(9) = 55 for a = b = 1
 #[derive(Default)]
-struct MyCircuit<F> {
-    pub a: Option<F>,
-    pub b: Option<F>,
+pub struct MyCircuit<F> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    // Number of additions, one row each
+    pub steps: usize,
+}
+
+impl<F: FieldExt> MyCircuit<F> {
+    // Rows used by the layout; the instance column needs three rows for a, b and out
+    pub fn rows(&self) -> usize {
+        self.steps.max(3)
+    }
+
+    // Smallest circuit size that fits all the steps plus the blinding rows
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
 }
 
 // Our circuit will instantiate an instance based on the interface defined on the chip and floorplanner (layouter)
@@ -181,7 +202,10 @@ impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
 
     // Circuit without witnesses, called only during key generation
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        Self {
+            steps: self.steps,
+            ..Self::default()
+        }
     }
 
     // Has the arrangement of columns. Called only during keygen, and will just call chip config most of the time
@@ -197,47 +221,111 @@ impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
     // Called both at key generation time, and proving time with a specific witness
     // Will call all of the copy constraints
     fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
         let chip = FiboChip::construct(config);
 
+        // F(0), ..., F(steps + 1), computed before any region is laid out
+        let values = self
+            .a
+            .zip(self.b)
+            .map(|(a, b)| FibonacciWitness::generate(a, b, self.steps).values)
+            .transpose_vec(self.steps + 2);
+
         let (prev_a, mut prev_b, mut prev_c) = chip.assign_first_row(
             layouter.namespace(|| "first row"),
-            self.a, self.b,
+            values[0], values[1], values[2],
         )?;
 
-        chip.expose_public(layouter.namespace(|| "private a"), &prev_a, 0)?;
-        chip.expose_public(layouter.namespace(|| "private b"), &prev_b, 1)?;
+        chip.expose_public(layouter.namespace(|| "private a"), &prev_a, PublicInputs::<F>::A_ROW)?;
+        chip.expose_public(layouter.namespace(|| "private b"), &prev_b, PublicInputs::<F>::B_ROW)?;
 
-        for _i in 3..10 {
+        for i in 1..self.steps {
             let c_cell = chip.assign_row(
                 layouter.namespace(|| "next row"),
                 &prev_b,
                 &prev_c,
+                values[i + 2],
             )?;
             prev_b = prev_c;
-            prev_c = c_cell; 
+            prev_c = c_cell;
         }
 
-        chip.expose_public(layouter.namespace(|| "output"), &prev_c, 2)?;
+        chip.expose_public(layouter.namespace(|| "output"), &prev_c, PublicInputs::<F>::OUT_ROW)?;
 
         Ok(())
     }
 }
 
-fn main() {
-    // Circuit size 
-    let k =4;
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::instance::InstanceColumn;
+
+    #[test]
+    fn test_fibonacci_three_columns() {
+        let a = Fp::from(1);     // F[0]
+        let b = Fp::from(1);    // F[1]
+        let out = Fp::from(55); // F[9]
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps: 8,
+        };
+        // Circuit size
+        let k = circuit.k().unwrap();
+        assert_eq!(k, 4);
+
+        let public_input = vec![a, b, out];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_fibonacci_three_columns_wrong_output() {
+        let a = Fp::from(1);
+        let b = Fp::from(1);
+        let out = Fp::from(56);
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps: 8,
+        };
 
-    let a =Fp::from(1);     // F[0]
-    let b = Fp::from(1);    // F[1]
-    let out = Fp::from(55); // F[9]
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![a, b, out]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 
-    let circuit = MyCircuit{
-        a: Some(a),
-        b: Some(b),
-    };
+    #[test]
+    fn test_fibonacci_three_columns_long_sequence() {
+        let (a, b) = (Fp::from(1), Fp::from(1));
+        let steps = 999; // F[1000]
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps,
+        };
+        let k = circuit.k().unwrap();
+        assert_eq!(k, 10);
+
+        let prover = MockProver::run(k, &circuit, PublicInputs::new(a, b, steps).instances()).unwrap();
+        prover.assert_satisfied();
+    }
 
-    let public_input = vec![a, b, out];
+    #[test]
+    fn test_fibonacci_three_columns_rejects_bad_lengths() {
+        let empty = MyCircuit::<Fp> { steps: 0, ..MyCircuit::default() };
+        assert_eq!(empty.k(), Err(SizeError::NoSteps));
 
-    let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-    prover.assert_satisfied();
+        let huge = MyCircuit::<Fp> { steps: 1 << 40, ..MyCircuit::default() };
+        assert!(matches!(huge.k(), Err(SizeError::TooManyRows { .. })));
+    }
 }
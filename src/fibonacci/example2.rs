@@ -6,19 +6,26 @@ This leads to faster proof generation
 use std::marker::PhantomData;
 
 use halo2_proofs::{
-    arithmetic::FieldExt, circuit::*, dev::MockProver, pasta::Fp, plonk::*, poly::Rotation
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
 };
 
-// Example
-// Row | a0 | a1 | a2 | s | i
-//  0  |  1 |  1 |  2 | 1 |
-//  1  |  1 |  2 |  3 | 1 |
-//  2  |  2 |  3 |  5 | 1 |
-//  3  |  3 |  5 |  8 | 1 |
-//  4  |  5 |  8 | 13 | 1 |
-// ...
+use super::PublicInputs;
+use crate::{
+    sizing::{self, SizeError},
+    witness::FibonacciWitness,
+};
 
-struct ACell<F: FieldExt>(AssignedCell<F, F>);
+// Example, from 1, 1 with 4 steps
+// Row | a0 | s | i
+//  0  |  1 | 1 | 1      <- seeds copied from the instance column
+//  1  |  1 | 1 | 1
+//  2  |  2 | 1 | 8      <- i at instance row 2 holds the output
+//  3  |  3 | 1 |
+//  4  |  5 |   |
+//  5  |  8 |   |        <- output, a0 in the last row, copied to instance row 2
+//
+// The gate at row r reads rows r, r + 1 and r + 2, so the last two rows of the table have
+// the selector turned off.
 
 // Defines the configuration of the columns
 // Advice columns hold the private inputs and other witnesses - vary over each proof
@@ -26,7 +33,7 @@ struct ACell<F: FieldExt>(AssignedCell<F, F>);
 // Fixed columns hold constants and lookup tables - circuit configuration
 // Selector columns hold control gates (binary constants) - circuit configuration
 #[derive(Debug, Clone)]
-struct FiboConfig {
+pub struct FiboConfig {
     // In this example we are using only one column in order to try to avoid permutation checks
     pub advice: Column<Advice>,
     pub selector: Selector,
@@ -34,14 +41,14 @@ struct FiboConfig {
 }
 
 // The chip struct configures the constraints in the circuit and provides assignment functions
-struct FiboChip<F: FieldExt> {
+pub struct FiboChip<F: FieldExt> {
     config: FiboConfig,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt> FiboChip<F> {
     // Default constructor
-    fn construct(config: FiboConfig) -> Self {
+    pub fn construct(config: FiboConfig) -> Self {
         Self { config, _marker: PhantomData}
     }
 
@@ -50,7 +57,7 @@ impl<F: FieldExt> FiboChip<F> {
     // There are two different kinds of selectors:
     //  - Selector - halo2 BE will apply optimizations to the selectors and combine them while preserving which gate gets turned on where
     //  - Complex Selector - used in lookup arguments, selector has to always be binary thus cannot apply selector combining optimizations
-    fn configure(
+    pub fn configure(
         meta: &mut ConstraintSystem<F>, 
         advice: Column<Advice>,
         instance: Column<Instance>
@@ -92,20 +99,30 @@ impl<F: FieldExt> FiboChip<F> {
     }
 
     // In this example we cannot assign row-by-row so we are assigning the entire table in one function
-    fn assign(
+    // `values` holds the whole sequence; the two seeds are read from the instance column instead
+    pub fn assign(
         &self, 
         mut layouter: impl Layouter<F>, 
-        nrows: usize,
+        values: &[Value<F>],
     ) -> Result<AssignedCell<F, F>, Error>{
+        let nrows = values.len();
+        // The two seeds at least
+        if nrows < 2 {
+            return Err(Error::Synthesis);
+        }
         layouter.assign_region(
             || "entire fibonacci table", 
             |mut region| {
-                self.config.selector.enable(&mut region, 0)?;
+                // The gate at row r reads rows r, r + 1 and r + 2, so it is enabled on
+                // every row except the last two of the table
+                for row in 0..nrows - 2 {
+                    self.config.selector.enable(&mut region, row)?;
+                }
 
-                let mut a_cell = region.assign_advice_from_instance(
+                region.assign_advice_from_instance(
                     || "1",
                     self.config.instance,
-                    0,
+                    PublicInputs::<F>::A_ROW,
                     self.config.advice,
                     0
                 )?;
@@ -113,30 +130,18 @@ impl<F: FieldExt> FiboChip<F> {
                 let mut b_cell = region.assign_advice_from_instance(
                     || "1",
                     self.config.instance,
-                    1,
+                    PublicInputs::<F>::B_ROW,
                     self.config.advice,
                     1
                 )?;
 
-                for row in 2..nrows {
-                    if row < nrows - 2 {
-                        self.config.selector.enable(&mut region, row)?;
-                    }
-                    let c_val = a_cell.value().and_then(
-                        |a| {
-                            b_cell.value().map(|b| *a + *b)
-                        }
-                    );
-
-                    let c_cell = region.assign_advice(
+                for (row, value) in values.iter().enumerate().skip(2) {
+                    b_cell = region.assign_advice(
                         || "advice", 
                         self.config.advice, 
                         row, 
-                        || c_val.ok_or(Error::Synthesis)
+                        || *value
                     )?;
-
-                    a_cell = b_cell;
-                    b_cell = c_cell;
                 }
 
                Ok(b_cell)
@@ -154,10 +159,29 @@ impl<F: FieldExt> FiboChip<F> {
     }
 }
 
+// The circuit proves out = F(steps + 1) for the sequence F(0) = a, F(1) = b,
+// e.g. steps = 8 gives F(9) = 55 for a = b = 1
 #[derive(Default)]
-struct MyCircuit<F> {
-    pub a: Option<F>,
-    pub b: Option<F>,
+pub struct MyCircuit<F> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    // Number of additions; the table holds the two seeds plus one row per step
+    pub steps: usize,
+}
+
+impl<F: FieldExt> MyCircuit<F> {
+    // Rows used by the single advice column
+    pub fn rows(&self) -> usize {
+        self.steps + 2
+    }
+
+    // Smallest circuit size that fits the whole table plus the blinding rows
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
 }
 
 // Our circuit will instantiate an instance based on the interface defined on the chip and floorplanner (layouter)
@@ -168,7 +192,10 @@ impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
 
     // Circuit without witnesses, called only during key generation
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        Self {
+            steps: self.steps,
+            ..Self::default()
+        }
     }
 
     // Has the arrangement of columns. Called only during keygen, and will just call chip config most of the time
@@ -182,34 +209,126 @@ impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
     // Called both at key generation time, and proving time with a specific witness
     // Will call all of the copy constraints
     fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
         let chip = FiboChip::construct(config);
 
+        let values = self
+            .a
+            .zip(self.b)
+            .map(|(a, b)| FibonacciWitness::generate(a, b, self.steps).values)
+            .transpose_vec(self.rows());
+
         let out_cell = chip.assign(
             layouter.namespace(|| "entire table"),
-            10
+            &values,
         )?;
 
-        chip.expose_public(layouter.namespace(|| "out"), out_cell, 2)?;
+        chip.expose_public(layouter.namespace(|| "out"), out_cell, PublicInputs::<F>::OUT_ROW)?;
 
         Ok(())
     }
 }
 
-fn main() {
-    // Circuit size 
-    let k =4;
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::instance::InstanceColumn;
+
+    #[test]
+    fn test_fibonacci_single_column() {
+        let a = Fp::from(1);     // F[0]
+        let b = Fp::from(1);    // F[1]
+        let out = Fp::from(55); // F[9]
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps: 8,
+        };
+        // Circuit size
+        let k = circuit.k().unwrap();
+        assert_eq!(k, 4);
+
+        let public_input = vec![a, b, out];
+
+        let prover = MockProver::run(k, &circuit, vec![public_input]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_fibonacci_single_column_wrong_output() {
+        let a = Fp::from(1);
+        let b = Fp::from(1);
+        let out = Fp::from(56);
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps: 8,
+        };
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![a, b, out]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 
-    let a =Fp::from(1);     // F[0]
-    let b = Fp::from(1);    // F[1]
-    let out = Fp::from(55); // F[9]
+    #[test]
+    fn test_fibonacci_single_column_long_sequence() {
+        let (a, b) = (Fp::from(1), Fp::from(1));
+        let steps = 999; // F[1000]
+
+        let circuit = MyCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps,
+        };
+        let k = circuit.k().unwrap();
+        assert_eq!(k, 10);
+
+        let prover = MockProver::run(k, &circuit, PublicInputs::new(a, b, steps).instances()).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_fibonacci_single_column_rejects_bad_lengths() {
+        let empty = MyCircuit::<Fp> { steps: 0, ..MyCircuit::default() };
+        assert_eq!(empty.k(), Err(SizeError::NoSteps));
 
-    let circuit = MyCircuit{
-        a: Some(a),
-        b: Some(b),
-    };
+        let huge = MyCircuit::<Fp> { steps: 1 << 40, ..MyCircuit::default() };
+        assert!(matches!(huge.k(), Err(SizeError::TooManyRows { .. })));
+    }
 
-    let public_input = vec![a, b, out];
+    // Hands the chip a table of `self.0` values
+    struct Table(usize);
 
-    let prover = MockProver::run(k, &circuit, vec![public_input.clone()]).unwrap();
-    prover.assert_satisfied();
-}
\ No newline at end of file
+    impl Circuit<Fp> for Table {
+        type Config = FiboConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self(self.0)
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            MyCircuit::configure(meta)
+        }
+
+        fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+            FiboChip::construct(config).assign(layouter, &vec![Value::known(Fp::one()); self.0])?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_single_column_chip_needs_seeds() {
+        let instance = vec![vec![Fp::one(), Fp::one()]];
+        for len in [0, 1] {
+            assert!(matches!(MockProver::run(4, &Table(len), instance.clone()), Err(Error::Synthesis)));
+        }
+        assert_eq!(MockProver::run(4, &Table(2), instance).unwrap().verify(), Ok(()));
+    }
+}
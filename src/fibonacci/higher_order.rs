@@ -0,0 +1,352 @@
+/*
+Single column circuit for order-K linear recurrences
+
+Generalises the single column fibonacci circuit to
+    s(i + K) = c(0) * s(i) + c(1) * s(i + 1) + ... + c(K - 1) * s(i + K - 1)
+e.g. Tribonacci (K = 3) or Tetranacci (K = 4) with all coefficients set to 1.
+*/
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
+};
+
+use crate::{
+    instance::InstanceColumn,
+    sizing::{self, SizeError},
+};
+
+// Example, Tribonacci (K = 3) from 0, 0, 1 with 4 steps
+// Row | a0 | s | i
+//  0  |  0 | 1 | 0      <- seeds copied from the instance column
+//  1  |  0 | 1 | 0
+//  2  |  1 | 1 | 1
+//  3  |  1 | 1 | 7      <- i at instance row K holds the output
+//  4  |  2 |   |
+//  5  |  4 |   |
+//  6  |  7 |   |        <- output, a0 in the last row, copied to instance row K
+//
+// The gate at row r reads rows r ..= r + K, so the last K rows of the table have the
+// selector turned off.
+
+// Coefficient vectors, chosen at configure time and baked into the gate as constants
+pub trait Coefficients<const K: usize> {
+    // c(0) multiplies the oldest value
+    fn coefficients<F: FieldExt>() -> [F; K];
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tribonacci;
+
+impl Coefficients<3> for Tribonacci {
+    fn coefficients<F: FieldExt>() -> [F; 3] {
+        [F::one(); 3]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tetranacci;
+
+impl Coefficients<4> for Tetranacci {
+    fn coefficients<F: FieldExt>() -> [F; 4] {
+        [F::one(); 4]
+    }
+}
+
+// Native reference for the circuit output: the last value after `steps` steps from `seeds`
+pub fn eval<F: FieldExt, const K: usize>(coefficients: &[F; K], seeds: [F; K], steps: usize) -> F {
+    let mut window = seeds;
+    for _ in 0..steps {
+        let next = coefficients.iter().zip(window.iter()).fold(F::zero(), |acc, (c, s)| acc + *c * s);
+        window.rotate_left(1);
+        window[K - 1] = next;
+    }
+    window[K - 1]
+}
+
+// Public inputs of the order-K circuit: the K seeds followed by the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<F, const K: usize> {
+    pub seeds: [F; K],
+    pub out: F,
+}
+
+impl<F: FieldExt, const K: usize> PublicInputs<F, K> {
+    // Seed i is at row SEEDS_ROW + i
+    pub const SEEDS_ROW: usize = 0;
+    pub const OUT_ROW: usize = K;
+
+    // Computes the output natively
+    pub fn new(coefficients: &[F; K], seeds: [F; K], steps: usize) -> Self {
+        Self { seeds, out: eval(coefficients, seeds, steps) }
+    }
+}
+
+impl<F: FieldExt, const K: usize> InstanceColumn<F> for PublicInputs<F, K> {
+    const LEN: usize = K + 1;
+
+    fn column(&self) -> Vec<F> {
+        self.seeds.into_iter().chain([self.out]).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HigherOrderConfig<F, const K: usize> {
+    pub advice: Column<Advice>,
+    pub selector: Selector,
+    pub instance: Column<Instance>,
+    pub coefficients: [F; K],
+}
+
+pub struct HigherOrderChip<F: FieldExt, const K: usize> {
+    config: HigherOrderConfig<F, K>,
+}
+
+impl<F: FieldExt, const K: usize> HigherOrderChip<F, K> {
+    pub fn construct(config: HigherOrderConfig<F, K>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: Column<Advice>,
+        instance: Column<Instance>,
+        coefficients: [F; K],
+    ) -> HigherOrderConfig<F, K> {
+        let selector = meta.selector();
+
+        meta.enable_equality(advice);
+        meta.enable_equality(instance);
+
+        meta.create_gate("linear recurrence", |meta| {
+            //
+            // col_a | selector
+            //  s(0) |    s
+            //  ...  |
+            //  s(K) |
+            let s = meta.query_selector(selector);
+            let next = meta.query_advice(advice, Rotation(K as i32));
+            let sum = coefficients
+                .iter()
+                .enumerate()
+                .fold(Expression::Constant(F::zero()), |acc, (i, c)| {
+                    acc + Expression::Constant(*c) * meta.query_advice(advice, Rotation(i as i32))
+                });
+            vec![s * (sum - next)]
+        });
+
+        HigherOrderConfig {
+            advice,
+            selector,
+            instance,
+            coefficients,
+        }
+    }
+
+    // Lays out the K seeds from the instance column followed by `steps` values,
+    // and returns the cell holding the last one
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        steps: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let nrows = K + steps;
+
+        layouter.assign_region(
+            || "entire recurrence table",
+            |mut region| {
+                for row in 0..nrows - K {
+                    self.config.selector.enable(&mut region, row)?;
+                }
+
+                let mut window = (0..K)
+                    .map(|row| {
+                        region.assign_advice_from_instance(
+                            || "seed",
+                            self.config.instance,
+                            PublicInputs::<F, K>::SEEDS_ROW + row,
+                            self.config.advice,
+                            row,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                for row in K..nrows {
+                    let next = self
+                        .config
+                        .coefficients
+                        .iter()
+                        .zip(window.iter())
+                        .fold(Value::known(F::zero()), |acc, (c, cell)| acc + cell.value().map(|v| *c * v));
+
+                    let cell = region.assign_advice(|| "advice", self.config.advice, row, || next)?;
+                    window.remove(0);
+                    window.push(cell);
+                }
+
+                Ok(window.pop().unwrap())
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: AssignedCell<F, F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.config.instance, row)
+    }
+}
+
+// Proves out = eval(C::coefficients(), seeds, steps) with the public inputs [seeds.., out].
+// The seeds are read from the instance column, so the circuit itself only holds the length.
+pub struct HigherOrderCircuit<F, C, const K: usize> {
+    pub steps: usize,
+    pub _marker: PhantomData<(F, C)>,
+}
+
+impl<F: FieldExt, C: Coefficients<K>, const K: usize> HigherOrderCircuit<F, C, K> {
+    pub fn new(steps: usize) -> Self {
+        Self { steps, _marker: PhantomData }
+    }
+
+    // Rows used by the single advice column
+    pub fn rows(&self) -> usize {
+        K + self.steps
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+
+    // Public inputs for the given seeds
+    pub fn public_inputs(&self, seeds: [F; K]) -> PublicInputs<F, K> {
+        PublicInputs::new(&C::coefficients(), seeds, self.steps)
+    }
+}
+
+impl<F: FieldExt, C: Coefficients<K>, const K: usize> Circuit<F> for HigherOrderCircuit<F, C, K> {
+    type Config = HigherOrderConfig<F, K>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(self.steps)
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        HigherOrderChip::configure(meta, advice, instance, C::coefficients())
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let chip = HigherOrderChip::construct(config);
+
+        let out_cell = chip.assign(layouter.namespace(|| "entire table"), self.steps)?;
+
+        chip.expose_public(layouter.namespace(|| "out"), out_cell, PublicInputs::<F, K>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::fibonacci::SingleColumnFiboCircuit;
+
+    struct Fibonacci;
+
+    impl Coefficients<2> for Fibonacci {
+        fn coefficients<F: FieldExt>() -> [F; 2] {
+            [F::one(); 2]
+        }
+    }
+
+    // s(i + 3) = 2 * s(i) - s(i + 1) + 3 * s(i + 2)
+    struct Custom;
+
+    impl Coefficients<3> for Custom {
+        fn coefficients<F: FieldExt>() -> [F; 3] {
+            [F::from(2), -F::one(), F::from(3)]
+        }
+    }
+
+    #[test]
+    fn test_tribonacci() {
+        let circuit = HigherOrderCircuit::<Fp, Tribonacci, 3>::new(8);
+        let public_inputs = circuit.public_inputs([Fp::zero(), Fp::zero(), Fp::one()]);
+        // T(10)
+        assert_eq!(public_inputs.out, Fp::from(81));
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_tetranacci() {
+        let circuit = HigherOrderCircuit::<Fp, Tetranacci, 4>::new(8);
+        let public_inputs = circuit.public_inputs([Fp::zero(), Fp::zero(), Fp::zero(), Fp::one()]);
+        // T(11)
+        assert_eq!(public_inputs.out, Fp::from(108));
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_custom_coefficients() {
+        let circuit = HigherOrderCircuit::<Fp, Custom, 3>::new(5);
+        let mut public_inputs = circuit.public_inputs([Fp::one(), Fp::from(2), Fp::from(3)]);
+        // 1, 2, 3, 9, 28, 81, 233, 674
+        assert_eq!(public_inputs.out, Fp::from(674));
+
+        let k = circuit.k().unwrap();
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+
+        public_inputs.out += Fp::one();
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_order_two_matches_single_column_chip() {
+        let (a, b) = (Fp::from(2), Fp::from(5));
+        for steps in 1..20 {
+            let circuit = HigherOrderCircuit::<Fp, Fibonacci, 2>::new(steps);
+            let instances = circuit.public_inputs([a, b]).instances();
+            assert_eq!(instances, crate::fibonacci::PublicInputs::new(a, b, steps).instances());
+
+            let single_column = SingleColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+            assert_eq!(circuit.k(), single_column.k());
+
+            let prover = MockProver::run(circuit.k().unwrap(), &circuit, instances).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_higher_order_tail() {
+        // With a single step only row 0 is constrained, and it reaches exactly the last row
+        let circuit = HigherOrderCircuit::<Fp, Tetranacci, 4>::new(1);
+        let public_inputs = circuit.public_inputs([Fp::one(), Fp::from(2), Fp::from(3), Fp::from(4)]);
+        assert_eq!(public_inputs.out, Fp::from(10));
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+
+        assert_eq!(HigherOrderCircuit::<Fp, Tribonacci, 3>::new(0).k(), Err(SizeError::NoSteps));
+    }
+}
@@ -0,0 +1,421 @@
+/*
+Integer fibonacci
+
+The other fibonacci circuits add field elements, so once F(n) passes the Pasta modulus the
+public output is F(n) mod p rather than the integer F(n). Here every value is split into
+LIMBS little-endian limbs of 8 bits, each limb is range checked with the lookup table from
+`range_check::example2`, and the additions propagate carries from limb to limb. The top
+limb may not carry out, so every value in the sequence is an integer below 2^(8 * LIMBS)
+and the proof certifies the actual integer, which can be larger than the field.
+*/
+
+use std::fmt;
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
+
+use crate::{
+    instance::InstanceColumn,
+    range_check::example2::table::RangeCheckTable,
+    sizing::{self, SizeError},
+};
+
+pub const LIMB_BITS: usize = 8;
+// Size of the lookup table every limb is checked against
+pub const LIMB_RANGE: usize = 1 << LIMB_BITS;
+
+// Example with LIMBS = 2, a = 200, b = 100
+// Row | l0  | l1 | k0 | q_add | q_range
+//  0  | 200 |  0 |    |   1   |    1
+//  1  | 100 |  0 |    |   1   |    1
+//  2  |  44 |  1 |  1 |   1   |    1       300 = 44 + 1 * 256
+//  3  | 144 |  1 |  0 |       |    1       400 = 144 + 1 * 256
+//
+// The gate at row r adds rows r and r + 1 into row r + 2, limb by limb, with the carry out
+// of limb j in column kj of row r + 2:
+//
+//   a_0 + b_0         = c_0 + 256 * k_0
+//   a_j + b_j + k_j-1 = c_j + 256 * k_j
+//   a_L + b_L + k_L-1 = c_L                   (no carry out of the top limb)
+
+// Little-endian base 2^8 digits of an integer below 2^(8 * LIMBS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limbs<const LIMBS: usize>(pub [u8; LIMBS]);
+
+impl<const LIMBS: usize> Default for Limbs<LIMBS> {
+    fn default() -> Self {
+        Self([0; LIMBS])
+    }
+}
+
+impl<const LIMBS: usize> Limbs<LIMBS> {
+    // None when the value needs more than LIMBS limbs
+    pub fn from_u128(value: u128) -> Option<Self> {
+        let bytes = value.to_le_bytes();
+        if bytes.iter().skip(LIMBS).any(|&byte| byte != 0) {
+            return None;
+        }
+        let mut limbs = [0; LIMBS];
+        for (limb, byte) in limbs.iter_mut().zip(bytes) {
+            *limb = byte;
+        }
+        Some(Self(limbs))
+    }
+
+    // The sum and the carry out of every limb, or None when the sum does not fit
+    pub fn checked_add(&self, other: &Self) -> Option<(Self, [u8; LIMBS])> {
+        let mut sum = [0; LIMBS];
+        let mut carries = [0; LIMBS];
+        let mut carry = 0u16;
+        for j in 0..LIMBS {
+            let total = self.0[j] as u16 + other.0[j] as u16 + carry;
+            sum[j] = (total % LIMB_RANGE as u16) as u8;
+            carry = total / LIMB_RANGE as u16;
+            carries[j] = carry as u8;
+        }
+        (carry == 0).then_some((Self(sum), carries))
+    }
+
+    pub fn to_field<F: FieldExt>(&self) -> [F; LIMBS] {
+        self.0.map(|limb| F::from(limb as u64))
+    }
+}
+
+// Addition `step` of the sequence (1-based) does not fit in LIMBS limbs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    pub step: usize,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} overflows the limbs", self.step)
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+// The whole sequence F(0) = a, F(1) = b, ..., F(steps + 1) with the carries of every addition.
+// The carries of row i are those of F(i - 2) + F(i - 1), and zero on the two seed rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegerWitness<const LIMBS: usize> {
+    pub values: Vec<Limbs<LIMBS>>,
+    pub carries: Vec<[u8; LIMBS]>,
+}
+
+impl<const LIMBS: usize> IntegerWitness<LIMBS> {
+    pub fn generate(a: Limbs<LIMBS>, b: Limbs<LIMBS>, steps: usize) -> Result<Self, Overflow> {
+        let mut values = vec![a, b];
+        let mut carries = vec![[0; LIMBS]; 2];
+        for i in 0..steps {
+            let (c, carry) = values[i].checked_add(&values[i + 1]).ok_or(Overflow { step: i + 1 })?;
+            values.push(c);
+            carries.push(carry);
+        }
+        Ok(Self { values, carries })
+    }
+
+    pub fn out(&self) -> Limbs<LIMBS> {
+        self.values[self.values.len() - 1]
+    }
+}
+
+// Public inputs: the limbs of a, b and out, one instance row per limb
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<const LIMBS: usize> {
+    pub a: Limbs<LIMBS>,
+    pub b: Limbs<LIMBS>,
+    pub out: Limbs<LIMBS>,
+}
+
+impl<const LIMBS: usize> PublicInputs<LIMBS> {
+    pub const A_ROW: usize = 0;
+    pub const B_ROW: usize = LIMBS;
+    pub const OUT_ROW: usize = 2 * LIMBS;
+
+    // Computes the output natively
+    pub fn new(a: Limbs<LIMBS>, b: Limbs<LIMBS>, steps: usize) -> Result<Self, Overflow> {
+        let out = IntegerWitness::generate(a, b, steps)?.out();
+        Ok(Self { a, b, out })
+    }
+}
+
+impl<F: FieldExt, const LIMBS: usize> InstanceColumn<F> for PublicInputs<LIMBS> {
+    const LEN: usize = 3 * LIMBS;
+
+    fn column(&self) -> Vec<F> {
+        [self.a, self.b, self.out].iter().flat_map(|limbs| limbs.to_field::<F>()).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IntegerFiboConfig<F: FieldExt, const LIMBS: usize> {
+    pub limbs: [Column<Advice>; LIMBS],
+    // Carry out of limbs 0 to LIMBS - 2; the top limb has none
+    pub carries: Vec<Column<Advice>>,
+    pub q_add: Selector,
+    pub q_range: Selector,
+    pub table: RangeCheckTable<F, LIMB_RANGE>,
+    pub instance: Column<Instance>,
+}
+
+pub struct IntegerFiboChip<F: FieldExt, const LIMBS: usize> {
+    config: IntegerFiboConfig<F, LIMBS>,
+}
+
+impl<F: FieldExt, const LIMBS: usize> IntegerFiboChip<F, LIMBS> {
+    pub fn construct(config: IntegerFiboConfig<F, LIMBS>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, instance: Column<Instance>) -> IntegerFiboConfig<F, LIMBS> {
+        assert!(LIMBS > 0);
+
+        let limbs = [(); LIMBS].map(|_| meta.advice_column());
+        let carries: Vec<_> = (1..LIMBS).map(|_| meta.advice_column()).collect();
+        let q_add = meta.selector();
+        let q_range = meta.complex_selector();
+        let table = RangeCheckTable::configure(meta);
+
+        for limb in limbs {
+            meta.enable_equality(limb);
+        }
+        meta.enable_equality(instance);
+
+        meta.create_gate("add limbs", |meta| {
+            let q_add = meta.query_selector(q_add);
+            let base = Expression::Constant(F::from(LIMB_RANGE as u64));
+
+            let mut constraints = vec![];
+            for j in 0..LIMBS {
+                let a = meta.query_advice(limbs[j], Rotation::cur());
+                let b = meta.query_advice(limbs[j], Rotation::next());
+                let c = meta.query_advice(limbs[j], Rotation(2));
+
+                let carry_in = match j {
+                    0 => Expression::Constant(F::zero()),
+                    _ => meta.query_advice(carries[j - 1], Rotation(2)),
+                };
+                let carry_out = match carries.get(j) {
+                    Some(&column) => {
+                        let carry = meta.query_advice(column, Rotation(2));
+                        // a limb sum is at most 2 * 255 + 1, so the carry is a bit
+                        constraints.push(carry.clone() * (Expression::Constant(F::one()) - carry.clone()));
+                        carry
+                    }
+                    None => Expression::Constant(F::zero()),
+                };
+
+                constraints.push(a + b + carry_in - c - base.clone() * carry_out);
+            }
+            Constraints::with_selector(q_add, constraints)
+        });
+
+        // Every limb of every row lies in [0, 2^8)
+        for limb in limbs {
+            meta.lookup(|meta| {
+                let q_range = meta.query_selector(q_range);
+                let value = meta.query_advice(limb, Rotation::cur());
+                vec![(q_range * value, table.value)]
+            });
+        }
+
+        IntegerFiboConfig {
+            limbs,
+            carries,
+            q_add,
+            q_range,
+            table,
+            instance,
+        }
+    }
+
+    // Lays out the whole sequence in one region and returns the limb cells of a, b and out
+    #[allow(clippy::type_complexity)]
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        values: &[Value<Limbs<LIMBS>>],
+        carries: &[Value<[u8; LIMBS]>],
+    ) -> Result<[Vec<AssignedCell<F, F>>; 3], Error> {
+        let nrows = values.len();
+
+        layouter.assign_region(
+            || "integer fibonacci table",
+            |mut region| {
+                let mut rows = vec![];
+                for (row, (value, carry)) in values.iter().zip(carries).enumerate() {
+                    self.config.q_range.enable(&mut region, row)?;
+                    if row + 2 < nrows {
+                        self.config.q_add.enable(&mut region, row)?;
+                    }
+
+                    let limbs = value.as_ref().map(|v| v.to_field::<F>()).transpose_array();
+                    let cells = limbs
+                        .iter()
+                        .zip(self.config.limbs)
+                        .map(|(limb, column)| region.assign_advice(|| "limb", column, row, || *limb))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    let carry = carry.as_ref().map(|c| c.map(|bit| F::from(bit as u64))).transpose_array();
+                    for (bit, column) in carry.iter().zip(&self.config.carries) {
+                        region.assign_advice(|| "carry", *column, row, || *bit)?;
+                    }
+
+                    rows.push(cells);
+                }
+
+                let out = rows.pop().unwrap();
+                let mut rows = rows.into_iter();
+                Ok([rows.next().unwrap(), rows.next().unwrap(), out])
+            },
+        )
+    }
+
+    // Constrains the limbs to instance rows row, ..., row + LIMBS - 1
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cells: &[AssignedCell<F, F>],
+        row: usize,
+    ) -> Result<(), Error> {
+        for (j, cell) in cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), self.config.instance, row + j)?;
+        }
+        Ok(())
+    }
+}
+
+// Proves out = F(steps + 1) over the integers for the sequence F(0) = a, F(1) = b
+#[derive(Default)]
+pub struct IntegerFiboCircuit<const LIMBS: usize> {
+    pub a: Value<Limbs<LIMBS>>,
+    pub b: Value<Limbs<LIMBS>>,
+    pub steps: usize,
+}
+
+impl<const LIMBS: usize> IntegerFiboCircuit<LIMBS> {
+    // The limb table, the lookup table and the instance column share the same rows
+    pub fn rows<F: FieldExt>(&self) -> usize {
+        (self.steps + 2).max(LIMB_RANGE).max(<PublicInputs<LIMBS> as InstanceColumn<F>>::LEN)
+    }
+
+    pub fn k<F: FieldExt>(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows::<F>())
+    }
+}
+
+impl<F: FieldExt, const LIMBS: usize> Circuit<F> for IntegerFiboCircuit<LIMBS> {
+    type Config = IntegerFiboConfig<F, LIMBS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            steps: self.steps,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let instance = meta.instance_column();
+        IntegerFiboChip::configure(meta, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        config.table.load(&mut layouter)?;
+        let chip = IntegerFiboChip::construct(config);
+
+        // A sequence that does not fit in the limbs has no witness at all
+        let witness = self.a.zip(self.b).map(|(a, b)| IntegerWitness::generate(a, b, self.steps));
+        witness.error_if_known_and(|w| w.is_err())?;
+        let witness = witness.map(|w| w.unwrap());
+
+        let nrows = self.steps + 2;
+        let values = witness.as_ref().map(|w| w.values.clone()).transpose_vec(nrows);
+        let carries = witness.map(|w| w.carries).transpose_vec(nrows);
+
+        let [a, b, out] = chip.assign(layouter.namespace(|| "sequence"), &values, &carries)?;
+
+        chip.expose_public(layouter.namespace(|| "a"), &a, PublicInputs::<LIMBS>::A_ROW)?;
+        chip.expose_public(layouter.namespace(|| "b"), &b, PublicInputs::<LIMBS>::B_ROW)?;
+        chip.expose_public(layouter.namespace(|| "out"), &out, PublicInputs::<LIMBS>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    fn to_biguint<const LIMBS: usize>(limbs: &Limbs<LIMBS>) -> BigUint {
+        BigUint::from_bytes_le(&limbs.0)
+    }
+
+    #[test]
+    fn test_limbs() {
+        assert_eq!(Limbs::<2>::from_u128(300), Some(Limbs([44, 1])));
+        assert_eq!(Limbs::<2>::from_u128(1 << 16), None);
+
+        let (sum, carries) = Limbs::<2>([200, 0]).checked_add(&Limbs([100, 0])).unwrap();
+        assert_eq!(sum, Limbs([44, 1]));
+        assert_eq!(carries, [1, 0]);
+
+        assert_eq!(Limbs::<2>([255, 255]).checked_add(&Limbs([1, 0])), None);
+    }
+
+    #[test]
+    fn test_integer_fibonacci_beyond_modulus() {
+        // F(368) has 255 bits, more than the Pasta modulus
+        const LIMBS: usize = 32;
+        let steps = 367;
+        let (a, b) = (Limbs::<LIMBS>::default(), Limbs::from_u128(1).unwrap());
+
+        let public_inputs = PublicInputs::new(a, b, steps).unwrap();
+
+        let (mut x, mut y) = (BigUint::from(0u32), BigUint::from(1u32));
+        for _ in 0..steps {
+            let next = &x + &y;
+            x = y;
+            y = next;
+        }
+        assert_eq!(to_biguint(&public_inputs.out), y);
+
+        let modulus = BigUint::parse_bytes(Fp::MODULUS.trim_start_matches("0x").as_bytes(), 16).unwrap();
+        assert!(y > modulus);
+
+        let circuit = IntegerFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+        let k = circuit.k::<Fp>().unwrap();
+        let prover = MockProver::<Fp>::run(k, &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+
+        // The output reduced modulo p, as the field circuits would prove it
+        let mut wrapped = public_inputs;
+        let reduced = (y % modulus).to_bytes_le();
+        wrapped.out = Limbs([0; LIMBS]);
+        wrapped.out.0[..reduced.len()].copy_from_slice(&reduced);
+        let prover = MockProver::<Fp>::run(k, &circuit, wrapped.instances()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_integer_fibonacci_overflow() {
+        // F(25) = 75025 does not fit in 16 bits
+        let (a, b) = (Limbs::<2>::default(), Limbs::from_u128(1).unwrap());
+        assert_eq!(PublicInputs::new(a, b, 23).unwrap().out, Limbs::from_u128(46368).unwrap());
+        assert_eq!(PublicInputs::new(a, b, 24), Err(Overflow { step: 24 }));
+
+        let circuit = IntegerFiboCircuit { a: Value::known(a), b: Value::known(b), steps: 24 };
+        let k = circuit.k::<Fp>().unwrap();
+        assert!(matches!(MockProver::<Fp>::run(k, &circuit, vec![vec![Fp::zero(); 6]]), Err(Error::Synthesis)));
+    }
+}
@@ -0,0 +1,299 @@
+/*
+Fibonacci circuit with a private index
+
+Proves out = F(n) for the sequence F(0) = a, F(1) = b without revealing n.
+The table always has MAX + 1 steps; a step counter is compared with the private n
+using the is-zero gadget, and only the matching row is copied into the output.
+*/
+
+use halo2_proofs::{
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
+};
+
+use super::PublicInputs;
+use crate::{
+    is_zero::{IsZeroChip, IsZeroConfig},
+    sizing::{self, SizeError},
+};
+
+// Example, a = b = 1, n = 3, max = 4
+// Row | x | y | idx | n | (idx - n)^-1 | done | out | s
+//  0  | 1 | 1 |  0  | 3 |    -1/3      |  0   |  0  | 1
+//  1  | 1 | 2 |  1  | 3 |    -1/2      |  0   |  0  | 1
+//  2  | 2 | 3 |  2  | 3 |    -1        |  0   |  0  | 1
+//  3  | 3 | 5 |  3  | 3 |     0        |  0   |  0  | 1    <- idx == n
+//  4  | 5 | 8 |  4  | 3 |     1        |  1   |  3  | 1
+//  5  | 8 | 13|  5  | 3 |              |  1   |  3  |
+//
+// x = F(idx), y = F(idx + 1). Once idx passes n the rest of the sequence keeps being
+// computed but no longer affects the output, since out only changes on the row where
+// idx == n. The done flag has to be 1 at the end, which rules out n > max.
+
+#[derive(Debug, Clone)]
+pub struct PrivateIndexConfig<F: FieldExt> {
+    pub x: Column<Advice>,
+    pub y: Column<Advice>,
+    pub idx: Column<Advice>,
+    pub n: Column<Advice>,
+    pub done: Column<Advice>,
+    pub out: Column<Advice>,
+    pub idx_equals_n: IsZeroConfig<F>,
+    pub constant: Column<Fixed>,
+    pub selector: Selector,
+    pub instance: Column<Instance>,
+}
+
+pub struct PrivateIndexChip<F: FieldExt> {
+    config: PrivateIndexConfig<F>,
+}
+
+impl<F: FieldExt> PrivateIndexChip<F> {
+    pub fn construct(config: PrivateIndexConfig<F>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 7],
+        constant: Column<Fixed>,
+        instance: Column<Instance>,
+    ) -> PrivateIndexConfig<F> {
+        let [x, y, idx, n, done, out, value_inv] = advice;
+        let selector = meta.selector();
+
+        meta.enable_constant(constant);
+        for column in [x, y, idx, done, out] {
+            meta.enable_equality(column);
+        }
+        meta.enable_equality(instance);
+
+        let idx_equals_n = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(selector),
+            |meta| meta.query_advice(idx, Rotation::cur()) - meta.query_advice(n, Rotation::cur()),
+            value_inv,
+        );
+
+        meta.create_gate("step", |meta| {
+            let s = meta.query_selector(selector);
+            let query = |meta: &mut VirtualCells<'_, F>, column| {
+                (meta.query_advice(column, Rotation::cur()), meta.query_advice(column, Rotation::next()))
+            };
+            let (x_cur, x_next) = query(meta, x);
+            let (y_cur, y_next) = query(meta, y);
+            let (idx_cur, idx_next) = query(meta, idx);
+            let (n_cur, n_next) = query(meta, n);
+            let (done_cur, done_next) = query(meta, done);
+            let (out_cur, out_next) = query(meta, out);
+            let is_eq = idx_equals_n.expr();
+
+            Constraints::with_selector(
+                s,
+                [
+                    // fibonacci step
+                    ("x", x_next - y_cur.clone()),
+                    ("y", y_next - (x_cur.clone() + y_cur)),
+                    // counter, and n carried down unchanged
+                    ("idx", idx_next - idx_cur - Expression::Constant(F::one())),
+                    ("n", n_next - n_cur),
+                    // the flag is raised and the value captured on the row where idx == n
+                    ("done", done_next - (done_cur + is_eq.clone())),
+                    ("out", out_next - (out_cur + is_eq * x_cur)),
+                ],
+            )
+        });
+
+        PrivateIndexConfig {
+            x,
+            y,
+            idx,
+            n,
+            done,
+            out,
+            idx_equals_n,
+            constant,
+            selector,
+            instance,
+        }
+    }
+
+    // Lays out `max + 1` steps and returns the cells holding a, b and F(n)
+    #[allow(clippy::type_complexity)]
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Value<F>,
+        b: Value<F>,
+        n: Value<u64>,
+        max: usize,
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>, AssignedCell<F, F>), Error> {
+        let is_zero_chip = IsZeroChip::construct(self.config.idx_equals_n.clone());
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "private index table",
+            |mut region| {
+                let a_cell = region.assign_advice(|| "a", config.x, 0, || a)?;
+                let b_cell = region.assign_advice(|| "b", config.y, 0, || b)?;
+                region.assign_advice_from_constant(|| "idx = 0", config.idx, 0, F::zero())?;
+                let mut done = region.assign_advice_from_constant(|| "done = 0", config.done, 0, F::zero())?;
+                let mut out = region.assign_advice_from_constant(|| "out = 0", config.out, 0, F::zero())?;
+
+                let n = n.map(|n| F::from(n));
+                let (mut x, mut y) = (a, b);
+
+                for row in 0..=max {
+                    config.selector.enable(&mut region, row)?;
+                    let idx = F::from(row as u64);
+                    region.assign_advice(|| "n", config.n, row, || n)?;
+                    is_zero_chip.assign(&mut region, row, n.map(|n| idx - n))?;
+
+                    let is_eq = n.map(|n| if n == idx { F::one() } else { F::zero() });
+                    let done_val = done.value().copied() + is_eq;
+                    let out_val = out.value().copied() + is_eq * x;
+                    (x, y) = (y, x + y);
+
+                    region.assign_advice(|| "x", config.x, row + 1, || x)?;
+                    region.assign_advice(|| "y", config.y, row + 1, || y)?;
+                    region.assign_advice(|| "idx", config.idx, row + 1, || Value::known(idx + F::one()))?;
+                    done = region.assign_advice(|| "done", config.done, row + 1, || done_val)?;
+                    out = region.assign_advice(|| "out", config.out, row + 1, || out_val)?;
+                }
+                region.assign_advice(|| "n", config.n, max + 1, || n)?;
+
+                // The flag has to be raised by the end of the table
+                region.constrain_constant(done.cell(), F::one())?;
+
+                Ok((a_cell, b_cell, out))
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: &AssignedCell<F, F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.config.instance, row)
+    }
+}
+
+// Proves out = F(n) for some private n <= max, with the public inputs [a, b, out]
+#[derive(Default)]
+pub struct PrivateIndexCircuit<F> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    pub n: Value<u64>,
+    // Largest index the circuit supports; it fixes the shape of the circuit
+    pub max: usize,
+}
+
+impl<F: FieldExt> PrivateIndexCircuit<F> {
+    // One row per step plus the final state
+    pub fn rows(&self) -> usize {
+        self.max + 2
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for PrivateIndexCircuit<F> {
+    type Config = PrivateIndexConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            max: self.max,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); 7].map(|_| meta.advice_column());
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+        PrivateIndexChip::configure(meta, advice, constant, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let chip = PrivateIndexChip::construct(config);
+
+        let (a_cell, b_cell, out_cell) = chip.assign(
+            layouter.namespace(|| "table"),
+            self.a,
+            self.b,
+            self.n,
+            self.max,
+        )?;
+
+        chip.expose_public(layouter.namespace(|| "a"), &a_cell, PublicInputs::<F>::A_ROW)?;
+        chip.expose_public(layouter.namespace(|| "b"), &b_cell, PublicInputs::<F>::B_ROW)?;
+        chip.expose_public(layouter.namespace(|| "out"), &out_cell, PublicInputs::<F>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+
+    fn circuit(n: u64, max: usize) -> PrivateIndexCircuit<Fp> {
+        PrivateIndexCircuit {
+            a: Value::known(Fp::one()),
+            b: Value::known(Fp::one()),
+            n: Value::known(n),
+            max,
+        }
+    }
+
+    fn fibonacci(n: u64) -> Fp {
+        (0..n).fold((Fp::one(), Fp::one()), |(a, b), _| (b, a + b)).0
+    }
+
+    #[test]
+    fn test_private_index() {
+        let max = 12;
+        for n in 0..=max as u64 {
+            let circuit = circuit(n, max);
+            let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![Fp::one(), Fp::one(), fibonacci(n)]]).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_private_index_wrong_output() {
+        let circuit = circuit(9, 12);
+        // F(9) = 55, F(10) = 89
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![Fp::one(), Fp::one(), fibonacci(10)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_private_index_out_of_range() {
+        // n beyond the table never matches the counter, so the flag is never raised
+        let circuit = circuit(13, 12);
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![Fp::one(), Fp::one(), Fp::zero()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_private_index_same_keys_for_every_n() {
+        use crate::prove;
+
+        let max = 12;
+        let params = prove::params(circuit(0, max).k().unwrap());
+        let pk = prove::keygen(&params, &circuit(0, max).without_witnesses()).unwrap();
+
+        for n in [3, 9] {
+            let instances = vec![vec![Fp::one(), Fp::one(), fibonacci(n)]];
+            let proof = prove::prove(&params, &pk, circuit(n, max), &instances).unwrap();
+            assert!(prove::verify(&params, pk.get_vk(), &proof, &instances).is_valid());
+        }
+    }
+}
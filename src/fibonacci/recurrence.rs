@@ -0,0 +1,367 @@
+/*
+Second-order linear recurrence circuit
+
+Generalises the three column fibonacci circuit to c = alpha * a + beta * b + gamma.
+The coefficients live in fixed columns, so they are part of the verifying key and the
+same chip proves Fibonacci, Lucas, Pell, Jacobsthal or any custom recurrence.
+*/
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation
+};
+
+use super::{example1::ACell, PublicInputs};
+use crate::sizing::{self, SizeError};
+
+// Example, Pell numbers (alpha = 1, beta = 2, gamma = 0)
+// Row | a0 | a1 | a2 | alpha | beta | gamma | s
+//  0  |  0 |  1 |  2 |   1   |  2   |   0   | 1
+//  1  |  1 |  2 |  5 |   1   |  2   |   0   | 1
+//  2  |  2 |  5 | 12 |   1   |  2   |   0   | 1
+//  3  |  5 | 12 | 29 |   1   |  2   |   0   | 1
+// ...
+
+// Coefficients of c = alpha * a + beta * b + gamma
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence<F> {
+    pub alpha: F,
+    pub beta: F,
+    pub gamma: F,
+}
+
+impl<F: FieldExt> Recurrence<F> {
+    pub fn new(alpha: F, beta: F, gamma: F) -> Self {
+        Self { alpha, beta, gamma }
+    }
+
+    // c = a + b, used by both the Fibonacci and the Lucas numbers
+    pub fn fibonacci() -> Self {
+        Self::new(F::one(), F::one(), F::zero())
+    }
+
+    // c = a + 2b
+    pub fn pell() -> Self {
+        Self::new(F::one(), F::from(2), F::zero())
+    }
+
+    // c = 2a + b
+    pub fn jacobsthal() -> Self {
+        Self::new(F::from(2), F::one(), F::zero())
+    }
+
+    // Native reference for a single step
+    pub fn next(&self, a: F, b: F) -> F {
+        self.alpha * a + self.beta * b + self.gamma
+    }
+
+    // Native reference for the circuit output: the last value after `steps` steps from (a, b)
+    pub fn eval(&self, a: F, b: F, steps: usize) -> F {
+        (0..steps).fold((a, b), |(a, b), _| (b, self.next(a, b))).1
+    }
+
+    // Public inputs of a circuit running `steps` steps of this recurrence from (a, b)
+    pub fn public_inputs(&self, a: F, b: F, steps: usize) -> PublicInputs<F> {
+        PublicInputs { a, b, out: self.eval(a, b, steps) }
+    }
+}
+
+// Well-known sequences, as a recurrence together with its usual seeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Fibonacci,
+    Lucas,
+    Pell,
+    Jacobsthal,
+}
+
+impl Preset {
+    pub fn recurrence<F: FieldExt>(&self) -> Recurrence<F> {
+        match self {
+            Preset::Fibonacci | Preset::Lucas => Recurrence::fibonacci(),
+            Preset::Pell => Recurrence::pell(),
+            Preset::Jacobsthal => Recurrence::jacobsthal(),
+        }
+    }
+
+    // (S(0), S(1))
+    pub fn seeds<F: FieldExt>(&self) -> (F, F) {
+        match self {
+            Preset::Lucas => (F::from(2), F::one()),
+            Preset::Fibonacci | Preset::Pell | Preset::Jacobsthal => (F::zero(), F::one()),
+        }
+    }
+
+    // Circuit proving S(steps + 1)
+    pub fn circuit<F: FieldExt>(&self, steps: usize) -> RecurrenceCircuit<F> {
+        let (a, b) = self.seeds();
+        RecurrenceCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            steps,
+            recurrence: self.recurrence(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecurrenceConfig {
+    pub advice: [Column<Advice>; 3],
+    // alpha, beta, gamma
+    pub coefficients: [Column<Fixed>; 3],
+    pub selector: Selector,
+    pub instance: Column<Instance>,
+}
+
+pub struct RecurrenceChip<F: FieldExt> {
+    config: RecurrenceConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> RecurrenceChip<F> {
+    pub fn construct(config: RecurrenceConfig) -> Self {
+        Self { config, _marker: PhantomData }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 3],
+        coefficients: [Column<Fixed>; 3],
+        instance: Column<Instance>,
+    ) -> RecurrenceConfig {
+        let [col_a, col_b, col_c] = advice;
+        let [col_alpha, col_beta, col_gamma] = coefficients;
+        let selector = meta.selector();
+
+        meta.enable_equality(col_a);
+        meta.enable_equality(col_b);
+        meta.enable_equality(col_c);
+        meta.enable_equality(instance);
+
+        meta.create_gate("linear recurrence", |meta| {
+            //
+            // col_a | col_b | col_c | alpha | beta | gamma | selector
+            //   a      b        c      al      be     ga       s
+            //
+            let s = meta.query_selector(selector);
+            let a = meta.query_advice(col_a, Rotation::cur());
+            let b = meta.query_advice(col_b, Rotation::cur());
+            let c = meta.query_advice(col_c, Rotation::cur());
+            let alpha = meta.query_fixed(col_alpha, Rotation::cur());
+            let beta = meta.query_fixed(col_beta, Rotation::cur());
+            let gamma = meta.query_fixed(col_gamma, Rotation::cur());
+            vec![s * (alpha * a + beta * b + gamma - c)]
+        });
+
+        RecurrenceConfig {
+            advice,
+            coefficients,
+            selector,
+            instance,
+        }
+    }
+
+    // Enables the gate on the row and writes the coefficients next to it
+    fn assign_coefficients(&self, region: &mut Region<'_, F>, recurrence: &Recurrence<F>) -> Result<(), Error> {
+        self.config.selector.enable(region, 0)?;
+        let [col_alpha, col_beta, col_gamma] = self.config.coefficients;
+        region.assign_fixed(|| "alpha", col_alpha, 0, || Value::known(recurrence.alpha))?;
+        region.assign_fixed(|| "beta", col_beta, 0, || Value::known(recurrence.beta))?;
+        region.assign_fixed(|| "gamma", col_gamma, 0, || Value::known(recurrence.gamma))?;
+        Ok(())
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn assign_first_row(
+        &self,
+        mut layouter: impl Layouter<F>,
+        recurrence: &Recurrence<F>,
+        a: Value<F>,
+        b: Value<F>,
+    ) -> Result<(ACell<F>, ACell<F>, ACell<F>), Error> {
+        layouter.assign_region(
+            || "first_row",
+            |mut region| {
+                self.assign_coefficients(&mut region, recurrence)?;
+
+                let a_cell = region.assign_advice(|| "a", self.config.advice[0], 0, || a).map(ACell)?;
+                let b_cell = region.assign_advice(|| "b", self.config.advice[1], 0, || b).map(ACell)?;
+
+                let c_val = a.zip(b).map(|(a, b)| recurrence.next(a, b));
+                let c_cell = region.assign_advice(|| "c", self.config.advice[2], 0, || c_val).map(ACell)?;
+
+                Ok((a_cell, b_cell, c_cell))
+            },
+        )
+    }
+
+    pub fn assign_row(
+        &self,
+        mut layouter: impl Layouter<F>,
+        recurrence: &Recurrence<F>,
+        prev_b: &ACell<F>,
+        prev_c: &ACell<F>,
+    ) -> Result<ACell<F>, Error> {
+        layouter.assign_region(
+            || "next row",
+            |mut region| {
+                self.assign_coefficients(&mut region, recurrence)?;
+
+                let a = prev_b.0.copy_advice(|| "a", &mut region, self.config.advice[0], 0)?;
+                let b = prev_c.0.copy_advice(|| "b", &mut region, self.config.advice[1], 0)?;
+
+                let c_val = a.value().zip(b.value()).map(|(a, b)| recurrence.next(*a, *b));
+                region.assign_advice(|| "c", self.config.advice[2], 0, || c_val).map(ACell)
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: &ACell<F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.0.cell(), self.config.instance, row)
+    }
+}
+
+// Proves out = recurrence.eval(a, b, steps) with the public inputs [a, b, out].
+// The coefficients are fixed at keygen, so each recurrence has its own keys.
+pub struct RecurrenceCircuit<F> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    // Number of recurrence steps, one row each
+    pub steps: usize,
+    pub recurrence: Recurrence<F>,
+}
+
+impl<F: FieldExt> RecurrenceCircuit<F> {
+    // Rows used by the layout; the instance column needs three rows for a, b and out
+    pub fn rows(&self) -> usize {
+        self.steps.max(3)
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for RecurrenceCircuit<F> {
+    type Config = RecurrenceConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            a: Value::unknown(),
+            b: Value::unknown(),
+            steps: self.steps,
+            recurrence: self.recurrence,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); 3].map(|_| meta.advice_column());
+        let coefficients = [(); 3].map(|_| meta.fixed_column());
+        let instance = meta.instance_column();
+        RecurrenceChip::configure(meta, advice, coefficients, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let chip = RecurrenceChip::construct(config);
+
+        let (prev_a, mut prev_b, mut prev_c) = chip.assign_first_row(
+            layouter.namespace(|| "first row"),
+            &self.recurrence,
+            self.a, self.b,
+        )?;
+
+        chip.expose_public(layouter.namespace(|| "private a"), &prev_a, PublicInputs::<F>::A_ROW)?;
+        chip.expose_public(layouter.namespace(|| "private b"), &prev_b, PublicInputs::<F>::B_ROW)?;
+
+        for _i in 1..self.steps {
+            let c_cell = chip.assign_row(
+                layouter.namespace(|| "next row"),
+                &self.recurrence,
+                &prev_b,
+                &prev_c,
+            )?;
+            prev_b = prev_c;
+            prev_c = c_cell;
+        }
+
+        chip.expose_public(layouter.namespace(|| "output"), &prev_c, PublicInputs::<F>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::{circuits::fibonacci, instance::InstanceColumn, prove};
+
+    fn run(preset: Preset, steps: usize, expected: u64) {
+        let (a, b) = preset.seeds::<Fp>();
+        let public_inputs = preset.recurrence::<Fp>().public_inputs(a, b, steps);
+        assert_eq!(public_inputs.out, Fp::from(expected));
+
+        let circuit = preset.circuit::<Fp>(steps);
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_recurrence_presets() {
+        // S(9)
+        run(Preset::Fibonacci, 8, 34);
+        run(Preset::Lucas, 8, 76);
+        run(Preset::Pell, 8, 985);
+        run(Preset::Jacobsthal, 8, 171);
+    }
+
+    #[test]
+    fn test_recurrence_matches_fibonacci_reference() {
+        let (a, b) = (Fp::from(3), Fp::from(7));
+        assert_eq!(Recurrence::fibonacci().eval(a, b, 20), fibonacci(a, b, 20));
+    }
+
+    #[test]
+    fn test_recurrence_custom_with_constant() {
+        // Leonardo numbers: c = a + b + 1 from (1, 1)
+        let recurrence = Recurrence::new(Fp::one(), Fp::one(), Fp::one());
+        let (a, b) = (Fp::one(), Fp::one());
+        let out = recurrence.eval(a, b, 8);
+        assert_eq!(out, Fp::from(109));
+
+        let circuit = RecurrenceCircuit { a: Value::known(a), b: Value::known(b), steps: 8, recurrence };
+        let k = circuit.k().unwrap();
+        let prover = MockProver::run(k, &circuit, vec![vec![a, b, out]]).unwrap();
+        prover.assert_satisfied();
+
+        // Without the constant the output is the plain Fibonacci one
+        let prover = MockProver::run(k, &circuit, vec![vec![a, b, fibonacci(a, b, 8)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_recurrence_coefficients_are_in_the_verifying_key() {
+        let pell = Preset::Pell.circuit::<Fp>(8);
+        let jacobsthal = Preset::Jacobsthal.circuit::<Fp>(8);
+        let params = prove::params(pell.k().unwrap());
+
+        let pell_vk = keygen_vk(&params, &pell.without_witnesses()).unwrap();
+        let jacobsthal_vk = keygen_vk(&params, &jacobsthal.without_witnesses()).unwrap();
+        assert_ne!(format!("{:?}", pell_vk.pinned()), format!("{:?}", jacobsthal_vk.pinned()));
+    }
+}
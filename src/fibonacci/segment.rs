@@ -0,0 +1,304 @@
+/*
+Segmented fibonacci proofs
+
+A long sequence is split into segments of `steps` additions each, proven separately with
+the three column chip. Every segment exposes the two values it starts from and the two
+values it ends with, so a verifier can check that consecutive segments link up and read
+the final value of the whole sequence from the last one.
+*/
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::*,
+    pasta::{EqAffine, Fp},
+    plonk::*,
+    poly::commitment::Params,
+};
+
+use super::example1::{FiboChip, FiboConfig};
+use crate::{
+    instance::InstanceColumn,
+    prove::{self, Verification},
+    sizing::{self, SizeError},
+    witness::FibonacciWitness,
+};
+
+// Segment i of a chain with `steps` additions per segment:
+//
+//   instance | value
+//      0     | F(i * steps)              in0
+//      1     | F(i * steps + 1)          in1
+//      2     | F((i + 1) * steps)        out0
+//      3     | F((i + 1) * steps + 1)    out1
+//
+// and out0, out1 of segment i are in0, in1 of segment i + 1.
+
+// Public inputs of a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<F> {
+    pub in0: F,
+    pub in1: F,
+    pub out0: F,
+    pub out1: F,
+}
+
+impl<F: FieldExt> PublicInputs<F> {
+    pub const IN0_ROW: usize = 0;
+    pub const IN1_ROW: usize = 1;
+    pub const OUT0_ROW: usize = 2;
+    pub const OUT1_ROW: usize = 3;
+
+    // Computes the outgoing values natively
+    pub fn new(in0: F, in1: F, steps: usize) -> Self {
+        let (out0, out1) = (0..steps).fold((in0, in1), |(a, b), _| (b, a + b));
+        Self { in0, in1, out0, out1 }
+    }
+
+    pub fn incoming(&self) -> (F, F) {
+        (self.in0, self.in1)
+    }
+
+    pub fn outgoing(&self) -> (F, F) {
+        (self.out0, self.out1)
+    }
+}
+
+impl<F: FieldExt> InstanceColumn<F> for PublicInputs<F> {
+    const LEN: usize = 4;
+
+    fn column(&self) -> Vec<F> {
+        vec![self.in0, self.in1, self.out0, self.out1]
+    }
+}
+
+// Proves that `steps` additions take (in0, in1) to (out0, out1)
+#[derive(Default)]
+pub struct SegmentCircuit<F> {
+    pub in0: Value<F>,
+    pub in1: Value<F>,
+    pub steps: usize,
+}
+
+impl<F: FieldExt> SegmentCircuit<F> {
+    // Rows used by the layout; the instance column needs four rows
+    pub fn rows(&self) -> usize {
+        self.steps.max(4)
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for SegmentCircuit<F> {
+    type Config = FiboConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            steps: self.steps,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); 3].map(|_| meta.advice_column());
+        let instance = meta.instance_column();
+        FiboChip::configure(meta, advice, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let chip = FiboChip::construct(config);
+        let values = self
+            .in0
+            .zip(self.in1)
+            .map(|(in0, in1)| FibonacciWitness::generate(in0, in1, self.steps).values)
+            .transpose_vec(self.steps + 2);
+
+        let (prev_a, mut prev_b, mut prev_c) = chip.assign_first_row(
+            layouter.namespace(|| "first row"),
+            values[0], values[1], values[2],
+        )?;
+
+        chip.expose_public(layouter.namespace(|| "in0"), &prev_a, PublicInputs::<F>::IN0_ROW)?;
+        chip.expose_public(layouter.namespace(|| "in1"), &prev_b, PublicInputs::<F>::IN1_ROW)?;
+
+        for i in 1..self.steps {
+            let c_cell = chip.assign_row(
+                layouter.namespace(|| "next row"),
+                &prev_b,
+                &prev_c,
+                values[i + 2],
+            )?;
+            prev_b = prev_c;
+            prev_c = c_cell;
+        }
+
+        chip.expose_public(layouter.namespace(|| "out0"), &prev_b, PublicInputs::<F>::OUT0_ROW)?;
+        chip.expose_public(layouter.namespace(|| "out1"), &prev_c, PublicInputs::<F>::OUT1_ROW)?;
+
+        Ok(())
+    }
+}
+
+// One proven segment together with its public inputs
+#[derive(Debug, Clone)]
+pub struct SegmentProof {
+    pub public_inputs: PublicInputs<Fp>,
+    pub proof: Vec<u8>,
+}
+
+// What a verified chain establishes: F(n) = out for the sequence F(0) = a, F(1) = b
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainClaim {
+    pub a: Fp,
+    pub b: Fp,
+    pub n: usize,
+    pub out: Fp,
+}
+
+#[derive(Debug)]
+pub enum ChainError {
+    Empty,
+    // The outgoing values of segment `index - 1` are not the incoming values of segment `index`
+    BrokenLink { index: usize },
+    // The proof of segment `index` does not verify
+    InvalidProof { index: usize, error: Error },
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::Empty => write!(f, "the chain has no segments"),
+            ChainError::BrokenLink { index } => {
+                write!(f, "segment {} does not start where segment {} ends", index, index - 1)
+            }
+            ChainError::InvalidProof { index, error } => write!(f, "segment {} does not verify: {:?}", index, error),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+// Proves F(0) = a, F(1) = b forward in `segments` segments of `steps` additions each
+pub fn prove_chain(
+    params: &Params<EqAffine>,
+    pk: &ProvingKey<EqAffine>,
+    (a, b): (Fp, Fp),
+    steps: usize,
+    segments: usize,
+) -> Result<Vec<SegmentProof>, Error> {
+    let mut incoming = (a, b);
+    (0..segments)
+        .map(|_| {
+            let public_inputs = PublicInputs::new(incoming.0, incoming.1, steps);
+            let circuit = SegmentCircuit {
+                in0: Value::known(incoming.0),
+                in1: Value::known(incoming.1),
+                steps,
+            };
+            let proof = prove::prove(params, pk, circuit, &public_inputs.instances())?;
+            incoming = public_inputs.outgoing();
+            Ok(SegmentProof { public_inputs, proof })
+        })
+        .collect()
+}
+
+// Checks that the segments link up and that every proof verifies against `vk`, which has
+// to be the key of `SegmentCircuit` with `steps` additions per segment
+pub fn verify_chain(
+    params: &Params<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    steps: usize,
+    segments: &[SegmentProof],
+) -> Result<ChainClaim, ChainError> {
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(ChainError::Empty),
+    };
+
+    // The links are cheap, so they are checked before any proof
+    for (index, pair) in segments.windows(2).enumerate() {
+        if pair[0].public_inputs.outgoing() != pair[1].public_inputs.incoming() {
+            return Err(ChainError::BrokenLink { index: index + 1 });
+        }
+    }
+
+    for (index, segment) in segments.iter().enumerate() {
+        if let Verification::Invalid(error) = prove::verify(params, vk, &segment.proof, &segment.public_inputs.instances()) {
+            return Err(ChainError::InvalidProof { index, error });
+        }
+    }
+
+    Ok(ChainClaim {
+        a: first.public_inputs.in0,
+        b: first.public_inputs.in1,
+        n: segments.len() * steps,
+        out: last.public_inputs.out0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+
+    use super::*;
+
+    fn fibonacci(n: usize) -> Fp {
+        (0..n).fold((Fp::zero(), Fp::one()), |(a, b), _| (b, a + b)).0
+    }
+
+    #[test]
+    fn test_segment() {
+        let circuit = SegmentCircuit { in0: Value::known(Fp::from(5)), in1: Value::known(Fp::from(8)), steps: 3 };
+        let k = circuit.k().unwrap();
+
+        let mut public_inputs = PublicInputs::new(Fp::from(5), Fp::from(8), 3);
+        assert_eq!(public_inputs.column(), [5, 8, 21, 34].map(Fp::from));
+
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+
+        public_inputs.out1 += Fp::one();
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_segment_chain() {
+        let steps = 10;
+        let params = prove::params(SegmentCircuit::<Fp> { steps, ..Default::default() }.k().unwrap());
+        let pk = prove::keygen(&params, &SegmentCircuit::<Fp> { steps, ..Default::default() }).unwrap();
+
+        let mut segments = prove_chain(&params, &pk, (Fp::zero(), Fp::one()), steps, 4).unwrap();
+
+        let claim = verify_chain(&params, pk.get_vk(), steps, &segments).unwrap();
+        assert_eq!(claim, ChainClaim { a: Fp::zero(), b: Fp::one(), n: 40, out: fibonacci(40) });
+
+        // A valid proof of a segment that does not continue the chain
+        let detached = prove_chain(&params, &pk, (Fp::one(), Fp::one()), steps, 1).unwrap();
+        let original = std::mem::replace(&mut segments[2], detached[0].clone());
+        assert!(matches!(
+            verify_chain(&params, pk.get_vk(), steps, &segments),
+            Err(ChainError::BrokenLink { index: 2 })
+        ));
+
+        // Linked public inputs with a proof that does not match them
+        segments[2] = SegmentProof { public_inputs: original.public_inputs, proof: segments[1].proof.clone() };
+        assert!(matches!(
+            verify_chain(&params, pk.get_vk(), steps, &segments),
+            Err(ChainError::InvalidProof { index: 2, .. })
+        ));
+
+        assert!(matches!(verify_chain(&params, pk.get_vk(), steps, &[]), Err(ChainError::Empty)));
+    }
+}
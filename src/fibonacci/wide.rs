@@ -0,0 +1,319 @@
+/*
+Configurable width fibonacci circuit
+
+Generalises the three column fibonacci circuit to W advice columns. Every row holds W
+consecutive values of the sequence, so it performs W - 2 additions, and only the last two
+values are copied into the next row. Wider rows mean fewer rows and fewer copy
+constraints, at the cost of more columns in the permutation and more commitments.
+*/
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::*,
+    pasta::Fp,
+    plonk::*,
+    poly::Rotation,
+};
+
+use super::{example1::ACell, PublicInputs};
+use crate::{
+    cost::{self, CostError},
+    sizing::{self, SizeError},
+    witness::FibonacciWitness,
+};
+
+// Example, W = 5
+// Row | a0 | a1 | a2 | a3 | a4 | s
+//  0  |  1 |  1 |  2 |  3 |  5 | 1
+//  1  |  3 |  5 |  8 | 13 | 21 | 1
+//  2  | 13 | 21 | 34 | 55 | 89 | 1
+// ...
+//
+// a0 and a1 of every row after the first are copies of a3 and a4 of the row above.
+
+#[derive(Debug, Clone)]
+pub struct WideFiboConfig<const W: usize> {
+    pub advice: [Column<Advice>; W],
+    pub selector: Selector,
+    pub instance: Column<Instance>,
+}
+
+pub struct WideFiboChip<F: FieldExt, const W: usize> {
+    config: WideFiboConfig<W>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const W: usize> WideFiboChip<F, W> {
+    // Additions performed by one row. Evaluating it rejects W < 3 at compile time, so no
+    // size is ever computed from a narrower row.
+    pub const ADDITIONS_PER_ROW: usize = {
+        assert!(W >= 3, "a row needs at least three columns to hold one addition");
+        W - 2
+    };
+
+    pub fn construct(config: WideFiboConfig<W>) -> Self {
+        Self { config, _marker: PhantomData }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; W],
+        instance: Column<Instance>,
+    ) -> WideFiboConfig<W> {
+        // Rejects W < 3
+        let _ = Self::ADDITIONS_PER_ROW;
+        let selector = meta.selector();
+
+        // Only the first two and the last two columns take part in copy constraints, but
+        // the output can land in any column, so all of them have equality enabled
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        meta.enable_equality(instance);
+
+        meta.create_gate("add", |meta| {
+            //
+            // a0 | a1 | a2 | ... | a(W-1) | selector
+            //
+            let s = meta.query_selector(selector);
+            let cells: Vec<_> = advice.iter().map(|column| meta.query_advice(*column, Rotation::cur())).collect();
+            cells
+                .windows(3)
+                .map(|w| s.clone() * (w[0].clone() + w[1].clone() - w[2].clone()))
+                .collect::<Vec<_>>()
+        });
+
+        WideFiboConfig {
+            advice,
+            selector,
+            instance,
+        }
+    }
+
+    // Fills the rest of a row whose first two cells are assigned, from the precomputed values
+    // of the row (see `witness::FibonacciWitness::row`)
+    fn assign_rest(
+        &self,
+        region: &mut Region<'_, F>,
+        mut cells: Vec<ACell<F>>,
+        values: Value<[F; W]>,
+    ) -> Result<Vec<ACell<F>>, Error> {
+        for column in 2..W {
+            let c_val = values.map(|values| values[column]);
+            let c_cell = region.assign_advice(|| "c", self.config.advice[column], 0, || c_val).map(ACell)?;
+            cells.push(c_cell);
+        }
+        Ok(cells)
+    }
+
+    // Returns all W cells of the row
+    pub fn assign_first_row(&self, mut layouter: impl Layouter<F>, values: Value<[F; W]>) -> Result<Vec<ACell<F>>, Error> {
+        layouter.assign_region(
+            || "first row",
+            |mut region| {
+                self.config.selector.enable(&mut region, 0)?;
+
+                let a_cell = region.assign_advice(|| "a", self.config.advice[0], 0, || values.map(|v| v[0])).map(ACell)?;
+                let b_cell = region.assign_advice(|| "b", self.config.advice[1], 0, || values.map(|v| v[1])).map(ACell)?;
+
+                self.assign_rest(&mut region, vec![a_cell, b_cell], values)
+            },
+        )
+    }
+
+    // Starts a row from the last two cells of `prev` and returns all W cells of the new row
+    pub fn assign_row(
+        &self,
+        mut layouter: impl Layouter<F>,
+        prev: &[ACell<F>],
+        values: Value<[F; W]>,
+    ) -> Result<Vec<ACell<F>>, Error> {
+        layouter.assign_region(
+            || "next row",
+            |mut region| {
+                self.config.selector.enable(&mut region, 0)?;
+
+                let a_cell = prev[W - 2].0.copy_advice(|| "a", &mut region, self.config.advice[0], 0).map(ACell)?;
+                let b_cell = prev[W - 1].0.copy_advice(|| "b", &mut region, self.config.advice[1], 0).map(ACell)?;
+
+                self.assign_rest(&mut region, vec![a_cell, b_cell], values)
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: &ACell<F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.0.cell(), self.config.instance, row)
+    }
+}
+
+// The circuit proves out = F(steps + 1) for the sequence F(0) = a, F(1) = b, like the
+// three column circuit. When `steps` is not a multiple of W - 2 the last row runs past
+// the output, and the output is taken from the middle of that row.
+#[derive(Default)]
+pub struct WideFiboCircuit<F, const W: usize> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    // Number of additions
+    pub steps: usize,
+}
+
+impl<F: FieldExt, const W: usize> WideFiboCircuit<F, W> {
+    // Rows of the sequence itself
+    pub fn sequence_rows(&self) -> usize {
+        self.steps.div_ceil(WideFiboChip::<F, W>::ADDITIONS_PER_ROW)
+    }
+
+    // Rows used by the layout; the instance column needs three rows for a, b and out
+    pub fn rows(&self) -> usize {
+        self.sequence_rows().max(3)
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        if self.steps == 0 {
+            return Err(SizeError::NoSteps);
+        }
+        sizing::minimal_k::<F, Self>(self.rows())
+    }
+}
+
+impl<F: FieldExt, const W: usize> Circuit<F> for WideFiboCircuit<F, W> {
+    type Config = WideFiboConfig<W>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            steps: self.steps,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); W].map(|_| meta.advice_column());
+        let instance = meta.instance_column();
+        WideFiboChip::configure(meta, advice, instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        if self.steps == 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let chip = WideFiboChip::construct(config);
+
+        // Every row of the layout, computed before any region is laid out. The last row can
+        // run past the output, so the sequence covers all the rows.
+        let witness = self
+            .a
+            .zip(self.b)
+            .map(|(a, b)| FibonacciWitness::generate(a, b, self.sequence_rows() * WideFiboChip::<F, W>::ADDITIONS_PER_ROW));
+        let values = |i: usize| witness.as_ref().map(|witness| witness.row::<W>(i));
+
+        let mut row = chip.assign_first_row(layouter.namespace(|| "first row"), values(0))?;
+
+        chip.expose_public(layouter.namespace(|| "private a"), &row[0], PublicInputs::<F>::A_ROW)?;
+        chip.expose_public(layouter.namespace(|| "private b"), &row[1], PublicInputs::<F>::B_ROW)?;
+
+        for i in 1..self.sequence_rows() {
+            row = chip.assign_row(layouter.namespace(|| "next row"), &row, values(i))?;
+        }
+
+        // Position of the last addition inside the last row
+        let column = (self.steps - 1) % WideFiboChip::<F, W>::ADDITIONS_PER_ROW + 2;
+        chip.expose_public(layouter.namespace(|| "output"), &row[column], PublicInputs::<F>::OUT_ROW)?;
+
+        Ok(())
+    }
+}
+
+// Size of the wide circuit for a given width and number of steps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WideReport {
+    pub width: usize,
+    pub steps: usize,
+    pub k: u32,
+    pub rows: usize,
+    pub advice_columns: usize,
+    // Columns taking part in the permutation argument, including the instance column
+    pub permutation_columns: usize,
+    // Copies between rows plus the three public inputs
+    pub copy_constraints: usize,
+    // Estimated by halo2's proof size model
+    pub proof_bytes: usize,
+}
+
+pub fn report<const W: usize>(steps: usize) -> Result<WideReport, CostError> {
+    let circuit = WideFiboCircuit::<Fp, W> { steps, ..Default::default() };
+    let k = circuit.k()?;
+    let rows = circuit.sequence_rows();
+    let cost = cost::measure(&circuit, k)?;
+
+    Ok(WideReport {
+        width: W,
+        steps,
+        k,
+        rows,
+        advice_columns: cost.advice_columns,
+        permutation_columns: cost.permutation_columns,
+        copy_constraints: 2 * (rows - 1) + 3,
+        // The circuit has copy constraints, so halo2's model always covers it
+        proof_bytes: cost.proof.map_or(0, |proof| proof.bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+
+    use super::*;
+    use crate::circuits::fibonacci;
+
+    fn run<const W: usize>(steps: usize) {
+        let (a, b) = (Fp::from(1), Fp::from(1));
+        let circuit = WideFiboCircuit::<Fp, W> { a: Value::known(a), b: Value::known(b), steps };
+
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![a, b, fibonacci(a, b, steps)]]).unwrap();
+        prover.assert_satisfied();
+
+        let wrong = fibonacci(a, b, steps + 1);
+        let prover = MockProver::run(circuit.k().unwrap(), &circuit, vec![vec![a, b, wrong]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_wide_fibonacci() {
+        // Multiples of W - 2 and leftovers in the last row
+        for steps in 1..=12 {
+            run::<3>(steps);
+            run::<5>(steps);
+            run::<8>(steps);
+        }
+    }
+
+    #[test]
+    fn test_wide_fibonacci_matches_three_columns() {
+        let three_columns = crate::fibonacci::ThreeColumnFiboCircuit::<Fp> { steps: 999, ..Default::default() };
+        let wide = WideFiboCircuit::<Fp, 3> { steps: 999, ..Default::default() };
+        assert_eq!(wide.rows(), three_columns.rows());
+        assert_eq!(wide.k(), three_columns.k());
+    }
+
+    #[test]
+    fn test_wide_report() {
+        let narrow = report::<3>(1000).unwrap();
+        assert_eq!((narrow.rows, narrow.k, narrow.copy_constraints), (1000, 10, 2001));
+
+        let wide = report::<10>(1000).unwrap();
+        assert_eq!((wide.rows, wide.k, wide.copy_constraints), (125, 8, 251));
+        assert_eq!((wide.advice_columns, wide.permutation_columns), (10, 11));
+
+        // Fewer rows, but more columns to commit to and open
+        assert!(wide.proof_bytes > narrow.proof_bytes);
+    }
+}
@@ -0,0 +1,20 @@
+// Public inputs laid out in a single instance column.
+//
+// Every circuit with public inputs has a `PublicInputs` type holding them, with one constant
+// per instance row. Synthesis takes the rows from there, and the prover and the verifier
+// take the column from this trait, so all three agree on the layout.
+
+use halo2_proofs::arithmetic::FieldExt;
+
+pub trait InstanceColumn<F: FieldExt> {
+    // Rows taken in the instance column
+    const LEN: usize;
+
+    // Values in instance row order
+    fn column(&self) -> Vec<F>;
+
+    // Public inputs for the prover and the verifier, one vector per instance column
+    fn instances(&self) -> Vec<Vec<F>> {
+        vec![self.column()]
+    }
+}
@@ -1,3 +1,5 @@
+pub mod example1;
+
 use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
 
 #[derive(Clone, Debug)]
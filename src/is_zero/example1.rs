@@ -0,0 +1,205 @@
+/*
+Example circuit using the is-zero gadget
+
+    f(a, b, c) = if a == b { c } else { a - b }
+*/
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
+
+use super::{IsZeroChip, IsZeroConfig};
+use crate::{instance::InstanceColumn, witness::IsZeroWitness};
+
+// Example
+// Row | a | b | c | out | a_minus_b_inv | s | i
+//  0  | 3 | 3 | 7 |  7  |      0        | 1 | 7
+//
+// The is-zero gadget is applied to the expression a - b, so the output gate can select
+// between c and a - b depending on whether the two inputs are equal
+#[derive(Debug, Clone)]
+pub struct FunctionConfig<F: FieldExt> {
+    pub selector: Selector,
+    pub a: Column<Advice>,
+    pub b: Column<Advice>,
+    pub c: Column<Advice>,
+    pub a_equals_b: IsZeroConfig<F>,
+    pub output: Column<Advice>,
+    pub instance: Column<Instance>,
+}
+
+pub struct FunctionChip<F: FieldExt> {
+    config: FunctionConfig<F>,
+}
+
+impl<F: FieldExt> FunctionChip<F> {
+    pub fn construct(config: FunctionConfig<F>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FunctionConfig<F> {
+        let selector = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let c = meta.advice_column();
+        let output = meta.advice_column();
+        let instance = meta.instance_column();
+
+        // Only the output is compared against the instance column
+        meta.enable_equality(output);
+        meta.enable_equality(instance);
+
+        let is_zero_advice_column = meta.advice_column();
+        let a_equals_b = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(selector),
+            |meta| meta.query_advice(a, Rotation::cur()) - meta.query_advice(b, Rotation::cur()),
+            is_zero_advice_column,
+        );
+
+        meta.create_gate("f(a, b, c) = if a == b {c} else {a - b}", |meta| {
+            let s = meta.query_selector(selector);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            let output = meta.query_advice(output, Rotation::cur());
+
+            vec![
+                // if a == b then output == c
+                s.clone() * (a_equals_b.expr() * (output.clone() - c)),
+                // if a != b then output == a - b
+                s * (Expression::Constant(F::one()) - a_equals_b.expr()) * (output - (a - b)),
+            ]
+        });
+
+        FunctionConfig {
+            selector,
+            a,
+            b,
+            c,
+            a_equals_b,
+            output,
+            instance,
+        }
+    }
+
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        witness: Value<&IsZeroWitness<F>>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let is_zero_chip = IsZeroChip::construct(self.config.a_equals_b.clone());
+        let (a, b, c) = (witness.map(|w| w.a), witness.map(|w| w.b), witness.map(|w| w.c));
+
+        layouter.assign_region(
+            || "f(a, b, c)",
+            |mut region| {
+                self.config.selector.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", self.config.a, 0, || a)?;
+                region.assign_advice(|| "b", self.config.b, 0, || b)?;
+                region.assign_advice(|| "c", self.config.c, 0, || c)?;
+                is_zero_chip.assign(&mut region, 0, a - b)?;
+
+                region.assign_advice(|| "output", self.config.output, 0, || witness.map(|w| w.out))
+            },
+        )
+    }
+
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cell: &AssignedCell<F, F>,
+        row: usize,         // Absolute index inside the instance column
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.config.instance, row)
+    }
+}
+
+// Public inputs of the circuit: only the output is public
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicInputs<F> {
+    pub out: F,
+}
+
+impl<F: FieldExt> PublicInputs<F> {
+    pub const OUT_ROW: usize = 0;
+
+    // Computes f(a, b, c) natively
+    pub fn new(a: F, b: F, c: F) -> Self {
+        IsZeroWitness::generate(a, b, c).public_inputs()
+    }
+}
+
+impl<F: FieldExt> InstanceColumn<F> for PublicInputs<F> {
+    const LEN: usize = 1;
+
+    fn column(&self) -> Vec<F> {
+        vec![self.out]
+    }
+}
+
+#[derive(Default)]
+pub struct MyCircuit<F> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    pub c: Value<F>,
+}
+
+impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
+    type Config = FunctionConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        FunctionChip::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let chip = FunctionChip::construct(config);
+        let witness = self.a.zip(self.b).zip(self.c).map(|((a, b), c)| IsZeroWitness::generate(a, b, c));
+        let out_cell = chip.assign(layouter.namespace(|| "f(a, b, c)"), witness.as_ref())?;
+        chip.expose_public(layouter.namespace(|| "out"), &out_cell, PublicInputs::<F>::OUT_ROW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::sizing::search_k;
+
+    #[test]
+    fn test_is_zero_equal_inputs() {
+        let circuit = MyCircuit {
+            a: Value::known(Fp::from(3)),
+            b: Value::known(Fp::from(3)),
+            c: Value::known(Fp::from(7)),
+        };
+        let k = search_k(&circuit, &[vec![Fp::from(7)]], 10).k.unwrap();
+
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(7)]]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_is_zero_different_inputs() {
+        let circuit = MyCircuit {
+            a: Value::known(Fp::from(10)),
+            b: Value::known(Fp::from(4)),
+            c: Value::known(Fp::from(7)),
+        };
+
+        let public_inputs = PublicInputs::new(Fp::from(10), Fp::from(4), Fp::from(7));
+        assert_eq!(public_inputs.out, Fp::from(6));
+        let k = search_k(&circuit, &public_inputs.instances(), 10).k.unwrap();
+
+        let prover = MockProver::run(k, &circuit, public_inputs.instances()).unwrap();
+        prover.assert_satisfied();
+
+        // Claiming the c branch when the inputs differ must fail
+        let prover = MockProver::run(k, &circuit, vec![vec![Fp::from(7)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
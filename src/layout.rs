@@ -0,0 +1,160 @@
+// Circuit layout rendering, available with the `dev-graph` feature.
+//
+// halo2's `CircuitLayout` draws one rectangle per region over the columns it uses, so
+// placing two circuits next to each other shows how they trade columns for rows, e.g.
+// the three-column and single-column Fibonacci circuits, or the gate-based and
+// lookup-based range checks. `circuit_dot_graph` exports the namespace tree built by
+// `layouter.namespace(...)` calls as a Graphviz DOT graph.
+
+use std::{error::Error, fs, path::Path};
+
+use halo2_proofs::{
+    dev::{circuit_dot_graph, CircuitLayout},
+    pasta::Fp,
+    plonk::Circuit,
+};
+use plotters::prelude::*;
+
+use crate::circuits::{CircuitKind, CircuitVisitor, Inputs};
+
+pub const DEFAULT_SIZE: (u32, u32) = (1024, 768);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Svg,
+    Dot,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Png, Format::Svg, Format::Dot];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            Format::Dot => "dot",
+        }
+    }
+
+    // Picks the format from the file extension of `path`
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?;
+        Format::ALL.into_iter().find(|format| format.extension() == extension)
+    }
+}
+
+fn circuit_layout() -> CircuitLayout {
+    CircuitLayout::default()
+        .show_labels(true)
+        .mark_equality_cells(true)
+        .show_equality_constraints(true)
+}
+
+// Renders the layout of `circuit` for a 2^k table to `path` as a PNG image
+pub fn render_png<C: Circuit<Fp>>(k: u32, circuit: &C, path: &Path, size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+    let root = BitMapBackend::new(path, size).into_drawing_area();
+    root.fill(&WHITE)?;
+    circuit_layout().render(k, circuit, &root)?;
+    root.present()?;
+    Ok(())
+}
+
+// Renders the layout of `circuit` for a 2^k table to `path` as an SVG image
+pub fn render_svg<C: Circuit<Fp>>(k: u32, circuit: &C, path: &Path, size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+    let root = SVGBackend::new(path, size).into_drawing_area();
+    root.fill(&WHITE)?;
+    circuit_layout().render(k, circuit, &root)?;
+    root.present()?;
+    Ok(())
+}
+
+// Returns the namespace tree of `circuit` as a DOT graph
+pub fn dot_graph<C: Circuit<Fp>>(circuit: &C) -> String {
+    circuit_dot_graph(circuit)
+}
+
+// Writes the circuit in the given format to `path`
+pub fn render<C: Circuit<Fp>>(k: u32, circuit: &C, path: &Path, format: Format) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Png => render_png(k, circuit, path, DEFAULT_SIZE),
+        Format::Svg => render_svg(k, circuit, path, DEFAULT_SIZE),
+        Format::Dot => Ok(fs::write(path, dot_graph(circuit))?),
+    }
+}
+
+// Renders a catalogued circuit; with `k` set, the table is drawn at that size instead
+// of the smallest one the circuit fits in
+pub struct Render<'a> {
+    pub path: &'a Path,
+    pub format: Format,
+    pub k: Option<u32>,
+}
+
+impl<'a> CircuitVisitor for Render<'a> {
+    type Output = Result<u32, Box<dyn Error>>;
+
+    fn visit<C: Circuit<Fp>>(self, circuit: C, _: Vec<Vec<Fp>>, k: u32) -> Self::Output {
+        let k = self.k.unwrap_or(k);
+        render(k, &circuit, self.path, self.format)?;
+        Ok(k)
+    }
+}
+
+// Renders every example circuit in every format into `dir`, as `<circuit id>.<extension>`
+pub fn render_all(dir: &Path, inputs: &Inputs) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    for kind in CircuitKind::ALL {
+        for format in Format::ALL {
+            let path = dir.join(kind.id()).with_extension(format.extension());
+            kind.visit(inputs, Render { path: &path, format, k: None })??;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_graph_lists_regions() {
+        let graph = CircuitKind::Fib3Col
+            .visit(&Inputs::default(), DotGraph)
+            .unwrap();
+        assert!(graph.starts_with("digraph"));
+        assert!(graph.contains("first row"));
+        assert!(graph.contains("next row"));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let path = std::env::temp_dir().join(format!("halo2-layout-{}.svg", std::process::id()));
+        let k = CircuitKind::RangeLookup
+            .visit(&Inputs::default(), Render { path: &path, format: Format::Svg, k: None })
+            .unwrap()
+            .unwrap();
+        assert_eq!(k, 10);
+
+        let svg = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("out/fib.svg")), Some(Format::Svg));
+        assert_eq!(Format::from_path(Path::new("fib.dot")), Some(Format::Dot));
+        assert_eq!(Format::from_path(Path::new("fib")), None);
+    }
+
+    struct DotGraph;
+
+    impl CircuitVisitor for DotGraph {
+        type Output = String;
+
+        fn visit<C: Circuit<Fp>>(self, circuit: C, _: Vec<Vec<Fp>>, _: u32) -> String {
+            dot_graph(&circuit)
+        }
+    }
+}
@@ -1,3 +1,18 @@
-mod range_check;
-// mod fibonacci;
-mod is_zero;
\ No newline at end of file
+pub mod range_check;
+pub mod fibonacci;
+pub mod circuits;
+pub mod is_zero;
+pub mod envelope;
+#[cfg(feature = "dev-graph")]
+pub mod layout;
+pub mod prove;
+pub mod sizing;
+pub mod witness;
+pub mod instance;
+pub mod planner;
+pub mod cost;
+
+pub use fibonacci::{
+    SingleColumnFiboChip, SingleColumnFiboCircuit, SingleColumnFiboConfig, ThreeColumnFiboChip,
+    ThreeColumnFiboCircuit, ThreeColumnFiboConfig,
+};
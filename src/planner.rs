@@ -0,0 +1,314 @@
+// Floor planners for the example circuits.
+//
+// Every circuit picks its floor planner in its `Circuit` impl: SimpleFloorPlanner for the
+// fibonacci and is-zero circuits, V1 for the range checks. `Planned<C, P>` is circuit `C`
+// laid out by planner `P` instead, so any circuit can be mocked, keyed and proven under
+// either planner without touching its definition:
+//
+//   let circuit = Planned::<_, V1>::new(ThreeColumnFiboCircuit { .. });
+//
+// `rows_used` runs only the layout pass, with no prover behind it, and reports how many rows
+// the planner ended up using. SimpleFloorPlanner places every region, in assignment order,
+// right below whatever is already assigned in the columns it touches; V1 first measures all
+// regions and then packs them. Regions that touch the same columns, like the one-row regions
+// of `FiboChip::assign_row`, end up stacked under either planner.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::{Field, FieldExt},
+    circuit::{floor_planner::V1, Layouter, SimpleFloorPlanner, Value},
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, FloorPlanner,
+        Instance, Selector,
+    },
+};
+
+// Circuit `C` laid out by floor planner `P`.
+//
+// A wrapper rather than a `P: FloorPlanner` parameter on every circuit: the planner is an
+// associated type of `Circuit`, so a parameter would have to be threaded through each circuit
+// struct, its constructors, the CLI's `CircuitKind` dispatch and every test and example that
+// names the circuit's type, for a choice only the planner comparisons make. The wrapper
+// forwards `configure` and `synthesize` unchanged, so the layout it produces is exactly the
+// one a circuit declaring `P` itself would get.
+pub struct Planned<C, P> {
+    pub circuit: C,
+    _planner: PhantomData<P>,
+}
+
+impl<C, P: FloorPlanner> Planned<C, P> {
+    pub fn new(circuit: C) -> Self {
+        Self {
+            circuit,
+            _planner: PhantomData,
+        }
+    }
+}
+
+impl<F: FieldExt, C: Circuit<F>, P: FloorPlanner> Circuit<F> for Planned<C, P> {
+    type Config = C::Config;
+    type FloorPlanner = P;
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(self.circuit.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.circuit.synthesize(config, layouter)
+    }
+}
+
+// Rows used by a circuit under both planners
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowCounts {
+    pub simple: usize,
+    pub v1: usize,
+}
+
+pub fn row_counts<F: FieldExt, C: Circuit<F>>(circuit: &C) -> Result<RowCounts, Error> {
+    Ok(RowCounts {
+        simple: rows_used::<F, C, SimpleFloorPlanner>(circuit)?,
+        v1: rows_used::<F, C, V1>(circuit)?,
+    })
+}
+
+// Number of rows planner `P` uses to lay out `circuit`: one past the last row holding an
+// assigned advice or fixed cell or an enabled selector. Lookup tables count, their padding
+// up to the end of the usable area does not.
+pub fn rows_used<F: FieldExt, C: Circuit<F>, P: FloorPlanner>(circuit: &C) -> Result<usize, Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+    let constants = constant_columns(&meta);
+
+    let mut counter = RowCounter::default();
+    P::synthesize(&mut counter, circuit, config, constants)?;
+    Ok(counter.rows)
+}
+
+// halo2 0.2 keeps the columns registered with `enable_constant` private, but lists them in
+// the debug output of the pinned constraint system, e.g.
+//
+//   constants: [Column { index: 1, column_type: Fixed }]
+//
+// Columns only carry their index and type, so allocating fixed columns in a scratch
+// constraint system up to the largest index yields equal column handles.
+fn constant_columns<F: Field>(meta: &ConstraintSystem<F>) -> Vec<Column<Fixed>> {
+    let pinned = format!("{:?}", meta.pinned());
+    let list = pinned
+        .split_once("constants: [")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(list, _)| list)
+        .unwrap_or_default();
+    let indices: Vec<usize> = list
+        .split("index: ")
+        .skip(1)
+        .filter_map(|entry| entry.split(',').next()?.trim().parse().ok())
+        .collect();
+
+    let mut scratch = ConstraintSystem::<F>::default();
+    let count = indices.iter().max().map_or(0, |max| max + 1);
+    let fixed: Vec<_> = (0..count).map(|_| scratch.fixed_column()).collect();
+    indices.into_iter().map(|index| fixed[index]).collect()
+}
+
+#[derive(Default)]
+struct RowCounter {
+    rows: usize,
+}
+
+impl RowCounter {
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+    }
+}
+
+impl<F: Field> Assignment<F> for RowCounter {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(&mut self, _: A, _: Column<Advice>, row: usize, _: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(&mut self, _: A, _: Column<Fixed>, row: usize, _: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Only used to pad lookup tables, which does not take up layout rows
+    fn fill_from_row(&mut self, _: Column<Fixed>, _: usize, _: Value<Assigned<F>>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+    use crate::{
+        fibonacci::{
+            batch::BatchCircuit,
+            doubling::{self, DoublingCircuit},
+            higher_order::{HigherOrderCircuit, Tribonacci},
+            integer::{self, IntegerFiboCircuit, Limbs},
+            private_index::PrivateIndexCircuit,
+            recurrence::Preset,
+            segment::{self, SegmentCircuit},
+            wide::WideFiboCircuit,
+            PublicInputs, SingleColumnFiboCircuit, ThreeColumnFiboCircuit,
+        },
+        instance::InstanceColumn,
+        is_zero, range_check,
+    };
+
+    // Mocks the circuit built by `circuit` under both planners
+    fn assert_satisfied_by_both<C: Circuit<Fp>>(circuit: impl Fn() -> C, k: u32, instances: Vec<Vec<Fp>>) {
+        let simple = Planned::<_, SimpleFloorPlanner>::new(circuit());
+        MockProver::run(k, &simple, instances.clone()).unwrap().assert_satisfied();
+
+        let v1 = Planned::<_, V1>::new(circuit());
+        MockProver::run(k, &v1, instances).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn test_every_circuit_under_both_planners() {
+        let (a, b, steps) = (Fp::one(), Fp::one(), 8);
+        let fibonacci = PublicInputs::new(a, b, steps).instances();
+
+        let circuit = || ThreeColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), fibonacci.clone());
+
+        let circuit = || SingleColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), fibonacci.clone());
+
+        let circuit = || WideFiboCircuit::<_, 6> { a: Value::known(a), b: Value::known(b), steps };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), fibonacci);
+
+        let circuit = || DoublingCircuit::<Fp>::new(11, 4);
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), doubling::PublicInputs::new(11).instances());
+
+        let circuit = || PrivateIndexCircuit { a: Value::known(a), b: Value::known(b), n: Value::known(5), max: 10 };
+        // F(5) = 8 for F(0) = F(1) = 1
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), vec![vec![a, b, Fp::from(8)]]);
+
+        let circuit = || Preset::Pell.circuit::<Fp>(steps);
+        let (a0, b0) = Preset::Pell.seeds::<Fp>();
+        let instances = Preset::Pell.recurrence::<Fp>().public_inputs(a0, b0, steps).instances();
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), instances);
+
+        let circuit = || HigherOrderCircuit::<Fp, Tribonacci, 3>::new(steps);
+        let instances = circuit().public_inputs([Fp::zero(), Fp::zero(), Fp::one()]).instances();
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), instances);
+
+        let seeds = [(Fp::zero(), Fp::one()), (Fp::from(2), Fp::from(3)), (Fp::from(5), Fp::from(8))];
+        let circuit = || BatchCircuit::<Fp, 2>::new(&seeds, steps);
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), BatchCircuit::<Fp, 2>::instances(&seeds, steps));
+
+        let circuit = || SegmentCircuit { in0: Value::known(a), in1: Value::known(b), steps };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), segment::PublicInputs::new(a, b, steps).instances());
+
+        let (x, y) = (Limbs::<2>::from_u128(1).unwrap(), Limbs::<2>::from_u128(1).unwrap());
+        let circuit = || IntegerFiboCircuit { a: Value::known(x), b: Value::known(y), steps };
+        let instances = integer::PublicInputs::new(x, y, steps).unwrap().instances();
+        assert_satisfied_by_both(circuit, circuit().k::<Fp>().unwrap(), instances);
+
+        let circuit = || is_zero::example1::MyCircuit {
+            a: Value::known(Fp::from(10)),
+            b: Value::known(Fp::from(4)),
+            c: Value::known(Fp::from(7)),
+        };
+        let instances = is_zero::example1::PublicInputs::new(Fp::from(10), Fp::from(4), Fp::from(7)).instances();
+        assert_satisfied_by_both(circuit, 4, instances);
+
+        let circuit = || range_check::example1::MyCircuit::<Fp, 8> { value: Value::known(Fp::from(7).into()) };
+        assert_satisfied_by_both(circuit, 4, vec![]);
+
+        let circuit = || range_check::example2::MyCircuit::<Fp, 8, 256> {
+            value: Value::known(Fp::from(7).into()),
+            large_value: Value::known(Fp::from(200).into()),
+        };
+        assert_satisfied_by_both(circuit, 10, vec![]);
+
+        let circuit = || range_check::decomposition::DecompositionCircuit::<Fp, 256> {
+            value: Value::known(Fp::from(1000)),
+            num_bits: 10,
+            strict: true,
+        };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), vec![]);
+
+        let circuit = || {
+            use range_check::interval::{BoundKind, IntervalCircuit};
+            IntervalCircuit::<Fp, 256>::new(30, 18, 121, BoundKind::Fixed, BoundKind::Instance).unwrap()
+        };
+        assert_satisfied_by_both(circuit, circuit().k().unwrap(), vec![circuit().public_inputs()]);
+    }
+
+    #[test]
+    fn test_rows_used() {
+        // One region per row, all over the same three columns: neither planner can pack them
+        let steps = 8;
+        let circuit = ThreeColumnFiboCircuit::<Fp> { steps, ..Default::default() };
+        assert_eq!(row_counts(&circuit).unwrap(), RowCounts { simple: steps, v1: steps });
+
+        // The constants region goes into the fixed column after the ladder
+        let circuit = DoublingCircuit::<Fp>::new(11, 4);
+        let counts = row_counts(&circuit).unwrap();
+        assert!(counts.simple >= circuit.rows());
+        assert!(counts.v1 >= circuit.rows());
+
+        // The two range-check regions use different selectors and share the value column
+        let circuit = range_check::example2::MyCircuit::<Fp, 8, 256>::default();
+        assert_eq!(row_counts(&circuit).unwrap(), RowCounts { simple: 511, v1: 511 });
+    }
+}
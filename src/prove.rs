@@ -0,0 +1,327 @@
+// Real proving and verification for the example circuits.
+//
+// Everything else in the crate stops at the MockProver, which checks the constraints
+// directly on the witness. This module runs the actual halo2 protocol over the Pasta
+// curves: IPA commitments over Vesta (`EqAffine`), with Fiat-Shamir challenges drawn
+// from a Blake2b transcript.
+
+use halo2_proofs::{
+    pasta::{EqAffine, Fp},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, BatchVerifier, Circuit, Error,
+        ProvingKey, SingleVerifier, VerifyingKey,
+    },
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use rand_core::OsRng;
+
+// Generates the IPA parameters for circuits of size 2^k.
+// There is no trusted setup, so these are deterministic for a given k.
+pub fn params(k: u32) -> Params<EqAffine> {
+    Params::new(k)
+}
+
+// Runs key generation for `circuit`.
+// Only the shape of the circuit matters here, so a circuit without witnesses is enough.
+pub fn keygen<C: Circuit<Fp>>(params: &Params<EqAffine>, circuit: &C) -> Result<ProvingKey<EqAffine>, Error> {
+    let vk = keygen_vk(params, circuit)?;
+    keygen_pk(params, vk, circuit)
+}
+
+// Creates a proof that `circuit` is satisfied for the given public inputs,
+// one vector per instance column.
+pub fn prove<C: Circuit<Fp>>(
+    params: &Params<EqAffine>,
+    pk: &ProvingKey<EqAffine>,
+    circuit: C,
+    instances: &[Vec<Fp>],
+) -> Result<Vec<u8>, Error> {
+    let instances: Vec<&[Fp]> = instances.iter().map(|column| column.as_slice()).collect();
+
+    let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, &[circuit], &[&instances], OsRng, &mut transcript)?;
+
+    Ok(transcript.finalize())
+}
+
+// Outcome of checking a proof against a verifying key and public inputs
+#[derive(Debug)]
+pub enum Verification {
+    Valid,
+    // The proof was rejected; the error tells whether it was malformed
+    // (`Error::Transcript`), had the wrong shape (`Error::InvalidInstances`)
+    // or simply did not verify (`Error::ConstraintSystemFailure`)
+    Invalid(Error),
+}
+
+impl Verification {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Verification::Valid)
+    }
+}
+
+// Verifies `proof` for the given public inputs, one vector per instance column.
+pub fn verify(
+    params: &Params<EqAffine>,
+    vk: &VerifyingKey<EqAffine>,
+    proof: &[u8],
+    instances: &[Vec<Fp>],
+) -> Verification {
+    let instances: Vec<&[Fp]> = instances.iter().map(|column| column.as_slice()).collect();
+
+    let strategy = SingleVerifier::new(params);
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(proof);
+
+    match verify_proof(params, vk, strategy, &[&instances], &mut transcript) {
+        Ok(()) => Verification::Valid,
+        Err(e) => Verification::Invalid(e),
+    }
+}
+
+// Proofs of the same circuit collected for a single batched check.
+//
+// halo2's `BatchVerifier` folds the final multi-scalar multiplication of every proof into
+// one, randomly scaled, so a batch costs little more than its largest proof. It only says
+// whether the whole batch is valid, so on failure the proofs are checked one by one to
+// find the culprits.
+#[derive(Debug, Default)]
+pub struct Batch {
+    items: Vec<(Vec<u8>, Vec<Vec<Fp>>)>,
+}
+
+// Outcome of checking a batch
+#[derive(Debug)]
+pub enum BatchVerification {
+    Valid,
+    // Index in the batch and error of every proof that did not verify
+    Invalid(Vec<(usize, Error)>),
+}
+
+impl BatchVerification {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, BatchVerification::Valid)
+    }
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a proof with its public inputs, one vector per instance column
+    pub fn add(&mut self, proof: Vec<u8>, instances: Vec<Vec<Fp>>) {
+        self.items.push((proof, instances));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Verifies every proof in the batch against `vk`
+    pub fn verify(self, params: &Params<EqAffine>, vk: &VerifyingKey<EqAffine>) -> BatchVerification {
+        let mut batch = BatchVerifier::new();
+        for (proof, instances) in &self.items {
+            batch.add_proof(vec![instances.clone()], proof.clone());
+        }
+        if batch.finalize(params, vk) {
+            return BatchVerification::Valid;
+        }
+
+        let failed: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (proof, instances))| match verify(params, vk, proof, instances) {
+                Verification::Valid => None,
+                Verification::Invalid(e) => Some((index, e)),
+            })
+            .collect();
+        BatchVerification::Invalid(failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{circuit::Value, plonk::Error};
+
+    use super::*;
+    use crate::{fibonacci, is_zero, range_check};
+
+    // Runs the whole pipeline and returns the verification outcome for the honest
+    // public inputs and for the tampered ones
+    fn prove_and_verify<C: Circuit<Fp>>(
+        k: u32,
+        circuit: C,
+        instances: Vec<Vec<Fp>>,
+        tampered: Option<Vec<Vec<Fp>>>,
+    ) -> (Verification, Option<Verification>) {
+        let params = params(k);
+        let pk = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove(&params, &pk, circuit, &instances).unwrap();
+
+        let honest = verify(&params, pk.get_vk(), &proof, &instances);
+        let tampered = tampered.map(|instances| verify(&params, pk.get_vk(), &proof, &instances));
+        (honest, tampered)
+    }
+
+    #[test]
+    fn test_prove_fibonacci_three_columns() {
+        let circuit = fibonacci::ThreeColumnFiboCircuit {
+            a: Value::known(Fp::from(1)),
+            b: Value::known(Fp::from(1)),
+            steps: 8,
+        };
+        let k = circuit.k().unwrap();
+
+        let (honest, tampered) = prove_and_verify(
+            k,
+            circuit,
+            vec![vec![Fp::from(1), Fp::from(1), Fp::from(55)]],
+            Some(vec![vec![Fp::from(1), Fp::from(1), Fp::from(56)]]),
+        );
+        assert!(honest.is_valid());
+        assert!(matches!(tampered, Some(Verification::Invalid(Error::ConstraintSystemFailure))));
+    }
+
+    #[test]
+    fn test_prove_fibonacci_single_column() {
+        let circuit = fibonacci::SingleColumnFiboCircuit {
+            a: Value::known(Fp::from(1)),
+            b: Value::known(Fp::from(1)),
+            steps: 8,
+        };
+        let k = circuit.k().unwrap();
+
+        let (honest, tampered) = prove_and_verify(
+            k,
+            circuit,
+            vec![vec![Fp::from(1), Fp::from(1), Fp::from(55)]],
+            Some(vec![vec![Fp::from(2), Fp::from(1), Fp::from(55)]]),
+        );
+        assert!(honest.is_valid());
+        assert!(matches!(tampered, Some(Verification::Invalid(Error::ConstraintSystemFailure))));
+    }
+
+    #[test]
+    fn test_prove_range_check_expression() {
+        let circuit = range_check::example1::MyCircuit::<Fp, 8> {
+            value: Value::known(Fp::from(5).into()),
+        };
+
+        // The circuit has no instance column, so adding one must be rejected
+        let (honest, tampered) = prove_and_verify(4, circuit, vec![], Some(vec![vec![Fp::from(5)]]));
+        assert!(honest.is_valid());
+        assert!(matches!(tampered, Some(Verification::Invalid(Error::InvalidInstances))));
+    }
+
+    #[test]
+    fn test_prove_range_check_lookup() {
+        let circuit = range_check::example2::MyCircuit::<Fp, 8, 256> {
+            value: Value::known(Fp::from(5).into()),
+            large_value: Value::known(Fp::from(200).into()),
+        };
+
+        let (honest, _) = prove_and_verify(10, circuit, vec![], None);
+        assert!(honest.is_valid());
+    }
+
+    #[test]
+    fn test_prove_is_zero() {
+        let circuit = is_zero::example1::MyCircuit {
+            a: Value::known(Fp::from(10)),
+            b: Value::known(Fp::from(4)),
+            c: Value::known(Fp::from(7)),
+        };
+
+        let (honest, tampered) = prove_and_verify(
+            4,
+            circuit,
+            vec![vec![Fp::from(6)]],
+            Some(vec![vec![Fp::from(7)]]),
+        );
+        assert!(honest.is_valid());
+        assert!(matches!(tampered, Some(Verification::Invalid(Error::ConstraintSystemFailure))));
+    }
+
+    #[test]
+    fn test_verify_rejects_truncated_proof() {
+        let circuit = is_zero::example1::MyCircuit {
+            a: Value::known(Fp::from(3)),
+            b: Value::known(Fp::from(3)),
+            c: Value::known(Fp::from(7)),
+        };
+        let instances = vec![vec![Fp::from(7)]];
+
+        let params = params(4);
+        let pk = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove(&params, &pk, circuit, &instances).unwrap();
+
+        let result = verify(&params, pk.get_vk(), &proof[..proof.len() / 2], &instances);
+        assert!(matches!(result, Verification::Invalid(Error::Transcript(_))));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let k = 4;
+        let params = params(k);
+        let pk = keygen(&params, &fibonacci::ThreeColumnFiboCircuit::<Fp> { steps: 8, ..Default::default() }).unwrap();
+
+        let mut batch = Batch::new();
+        let mut bad = Batch::new();
+        for a in 0..6u64 {
+            let (a, b) = (Fp::from(a), Fp::from(1));
+            let circuit = fibonacci::ThreeColumnFiboCircuit { a: Value::known(a), b: Value::known(b), steps: 8 };
+            let instances = vec![vec![a, b, crate::circuits::fibonacci(a, b, 8)]];
+            let proof = prove(&params, &pk, circuit, &instances).unwrap();
+
+            // Proofs 2 and 5 are checked against the wrong output
+            let mut tampered = instances.clone();
+            if a == Fp::from(2) || a == Fp::from(5) {
+                tampered[0][2] += Fp::one();
+            }
+            batch.add(proof.clone(), instances);
+            bad.add(proof, tampered);
+        }
+        assert_eq!(batch.len(), 6);
+
+        assert!(batch.verify(&params, pk.get_vk()).is_valid());
+        match bad.verify(&params, pk.get_vk()) {
+            BatchVerification::Invalid(failed) => {
+                let indices: Vec<_> = failed.iter().map(|(index, _)| *index).collect();
+                assert_eq!(indices, vec![2, 5]);
+            }
+            BatchVerification::Valid => panic!("tampered batch verified"),
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_range_check() {
+        let params = params(10);
+        let pk = keygen(&params, &range_check::example2::MyCircuit::<Fp, 8, 256>::default()).unwrap();
+
+        let mut batch = Batch::new();
+        for value in [7u64, 200] {
+            let circuit = range_check::example2::MyCircuit::<Fp, 8, 256> {
+                value: Value::known(Fp::from(value % 8).into()),
+                large_value: Value::known(Fp::from(value).into()),
+            };
+            batch.add(prove(&params, &pk, circuit, &[]).unwrap(), vec![]);
+        }
+        // A truncated proof
+        let (proof, _) = batch.items[1].clone();
+        batch.add(proof[..proof.len() / 2].to_vec(), vec![]);
+
+        match batch.verify(&params, pk.get_vk()) {
+            BatchVerification::Invalid(failed) => {
+                let indices: Vec<_> = failed.iter().map(|(index, _)| *index).collect();
+                assert_eq!(indices, vec![2]);
+            }
+            BatchVerification::Valid => panic!("truncated proof verified"),
+        }
+    }
+}
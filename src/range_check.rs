@@ -1,2 +1,5 @@
-mod example1;
-mod example2;
\ No newline at end of file
+pub mod example1;
+pub mod example2;
+pub mod decomposition;
+pub mod interval;
+pub mod auto;
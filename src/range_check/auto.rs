@@ -0,0 +1,291 @@
+// This helper checks v < RANGE with whichever of the other range checks keeps the constraint
+// system under a given degree. The expression gate of example1 has degree RANGE + 1, so it is
+// only cheap for small ranges; past that the check goes to the lookup of example2 (degree 5),
+// and ranges larger than the lookup table go to the interval chip over the decomposition
+// (degree 6).
+//
+//      strategy      |   degree     |   rows
+//  ----------------------------------------------------------------
+//       gate         |  RANGE + 1   |   1
+//       lookup       |      5       |   table
+//   decomposition    |      6       |   table, or the running sums
+//
+// The table strategies are configured into a scratch constraint system to read their degree,
+// and the first strategy within the budget is kept.
+
+use std::fmt;
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*};
+
+use super::{
+    decomposition::DecompositionConfig,
+    example1,
+    example2::{self, table::{self, RangeCheckTable}},
+    interval::{Bound, IntervalConfig},
+};
+use crate::sizing::{self, SizeError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Gate,
+    Lookup,
+    Decomposition,
+}
+
+impl Strategy {
+    // In order of preference
+    pub const ALL: [Strategy; 3] = [Strategy::Gate, Strategy::Lookup, Strategy::Decomposition];
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Gate => write!(f, "gate"),
+            Strategy::Lookup => write!(f, "lookup"),
+            Strategy::Decomposition => write!(f, "decomposition"),
+        }
+    }
+}
+
+// No strategy fits the degree budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DegreeError {
+    pub max_degree: usize,
+    // Lowest degree any strategy reaches
+    pub needed: usize,
+}
+
+impl fmt::Display for DegreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no range check fits in degree {}, the cheapest needs {}",
+            self.max_degree, self.needed
+        )
+    }
+}
+
+impl std::error::Error for DegreeError {}
+
+impl From<DegreeError> for SizeError {
+    fn from(e: DegreeError) -> Self {
+        SizeError::DegreeTooHigh { max_degree: e.max_degree, needed: e.needed }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RangeCheckChip<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    Gate(example1::RangeCheckConfig<F, RANGE>),
+    // With a gate range of 1 the expression gate of example2 stays at degree 2 and every
+    // range other than 1 goes to the table
+    Lookup(example2::RangeCheckConfig<F, 1, LOOKUP_RANGE>),
+    Decomposition(IntervalConfig<F, LOOKUP_RANGE>),
+}
+
+#[derive(Debug, Clone)]
+pub struct AutoRangeCheckConfig<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    pub strategy: Strategy,
+    // Degree of the constraint system once the check is configured
+    pub degree: usize,
+    pub chip: RangeCheckChip<F, RANGE, LOOKUP_RANGE>,
+}
+
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> AutoRangeCheckConfig<F, RANGE, LOOKUP_RANGE> {
+    // Configures the check with a strategy picked by `select`
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        strategy: Strategy,
+    ) -> Self {
+        let chip = Self::configure_chip(meta, value, strategy);
+
+        Self {
+            strategy,
+            degree: meta.degree(),
+            chip,
+        }
+    }
+
+    // The first strategy whose constraints stay within `max_degree`
+    pub fn select(max_degree: usize) -> Result<Strategy, DegreeError> {
+        let candidates = Strategy::ALL
+            .into_iter()
+            .filter(|strategy| *strategy != Strategy::Lookup || RANGE <= LOOKUP_RANGE);
+
+        let mut needed = usize::MAX;
+        for strategy in candidates {
+            let degree = Self::degree(strategy);
+            if degree <= max_degree {
+                return Ok(strategy);
+            }
+            needed = needed.min(degree);
+        }
+        Err(DegreeError { max_degree, needed })
+    }
+
+    // Degree of a constraint system holding only the check
+    pub fn degree(strategy: Strategy) -> usize {
+        let mut meta = ConstraintSystem::default();
+        let value = meta.advice_column();
+        if strategy == Strategy::Gate {
+            // One factor per value and the selector. The expression nests RANGE deep, so it is
+            // not built just to be measured.
+            return meta.degree().max(RANGE + 1);
+        }
+        Self::configure_chip(&mut meta, value, strategy);
+        meta.degree()
+    }
+
+    // Rows taken by a check, including the lookup table
+    pub fn rows(strategy: Strategy) -> Result<usize, SizeError> {
+        Ok(match strategy {
+            Strategy::Gate => 1,
+            Strategy::Lookup => table::table_rows(LOOKUP_RANGE),
+            Strategy::Decomposition => IntervalConfig::<F, LOOKUP_RANGE>::rows(Self::bits()?)?
+                .max(RangeCheckTable::<F, LOOKUP_RANGE>::ROWS),
+        })
+    }
+
+    fn bits() -> Result<usize, SizeError> {
+        IntervalConfig::<F, LOOKUP_RANGE>::width_bits(0, RANGE as u64)
+    }
+
+    fn configure_chip(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        strategy: Strategy,
+    ) -> RangeCheckChip<F, RANGE, LOOKUP_RANGE> {
+        match strategy {
+            Strategy::Gate => RangeCheckChip::Gate(example1::RangeCheckConfig::configure(meta, value)),
+            Strategy::Lookup => RangeCheckChip::Lookup(example2::RangeCheckConfig::configure(meta, value)),
+            Strategy::Decomposition => {
+                let running_sum = meta.advice_column();
+                let constant = meta.fixed_column();
+                let table = RangeCheckTable::configure(meta);
+                let decomposition = DecompositionConfig::configure(meta, running_sum, constant, table);
+                RangeCheckChip::Decomposition(IntervalConfig::configure(meta, value, decomposition))
+            }
+        }
+    }
+
+    // Loads the lookup table, if the strategy has one
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        match &self.chip {
+            RangeCheckChip::Gate(_) => Ok(()),
+            RangeCheckChip::Lookup(config) => config.table.load(layouter),
+            RangeCheckChip::Decomposition(config) => config.decomposition.table.load(layouter),
+        }
+    }
+
+    pub fn assign(&self, layouter: impl Layouter<F>, value: Value<F>) -> Result<(), Error> {
+        match &self.chip {
+            RangeCheckChip::Gate(config) => config.assign(layouter, value.map(Assigned::from)),
+            RangeCheckChip::Lookup(config) => config.assign(layouter, value.map(Assigned::from), RANGE),
+            RangeCheckChip::Decomposition(config) => {
+                let lo = Bound::Constant(F::zero());
+                let hi = Bound::Constant(F::from(RANGE as u64));
+                let bits = Self::bits().map_err(|_| Error::Synthesis)?;
+                config.assign(layouter, value, &lo, &hi, bits)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+// Checks a single value against RANGE within a degree budget of MAX_DEGREE
+#[derive(Default)]
+pub struct AutoRangeCircuit<F, const RANGE: usize, const LOOKUP_RANGE: usize, const MAX_DEGREE: usize> {
+    pub value: Value<F>,
+}
+
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize, const MAX_DEGREE: usize>
+    AutoRangeCircuit<F, RANGE, LOOKUP_RANGE, MAX_DEGREE>
+{
+    pub fn strategy() -> Result<Strategy, DegreeError> {
+        AutoRangeCheckConfig::<F, RANGE, LOOKUP_RANGE>::select(MAX_DEGREE)
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        let strategy = Self::strategy()?;
+        let rows = AutoRangeCheckConfig::<F, RANGE, LOOKUP_RANGE>::rows(strategy)?;
+        sizing::minimal_k::<F, Self>(rows)
+    }
+}
+
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize, const MAX_DEGREE: usize> Circuit<F>
+    for AutoRangeCircuit<F, RANGE, LOOKUP_RANGE, MAX_DEGREE>
+{
+    type Config = AutoRangeCheckConfig<F, RANGE, LOOKUP_RANGE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        // `k` reports a circuit outside its budget as an error; configure has no way to
+        // return one, so such a circuit stops here
+        let strategy = Self::strategy().unwrap_or_else(|e| panic!("{}", e));
+        let value = meta.advice_column();
+        AutoRangeCheckConfig::configure(meta, value, strategy)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        config.assign(layouter.namespace(|| "value"), self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, pasta::Fp};
+
+    use super::*;
+
+    // Checks that the circuit takes `strategy` at `degree`, accepts RANGE - 1 and rejects RANGE
+    fn check<const RANGE: usize, const MAX_DEGREE: usize>(strategy: Strategy, degree: usize) {
+        type Auto<const R: usize, const D: usize> = AutoRangeCircuit<Fp, R, 256, D>;
+
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let config = Auto::<RANGE, MAX_DEGREE>::configure(&mut meta);
+        assert_eq!((config.strategy, config.degree), (strategy, degree));
+        assert!(degree <= MAX_DEGREE);
+
+        let circuit = |value: u64| Auto::<RANGE, MAX_DEGREE> { value: Value::known(Fp::from(value)) };
+        let k = circuit(0).k().unwrap();
+        assert_eq!(MockProver::run(k, &circuit(RANGE as u64 - 1), vec![]).unwrap().verify(), Ok(()));
+        assert!(MockProver::run(k, &circuit(RANGE as u64), vec![]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_auto_strategy() {
+        // v * (1 - v) * ... * (7 - v) under a selector
+        check::<8, 9>(Strategy::Gate, 9);
+        check::<8, 8>(Strategy::Lookup, 5);
+        check::<64, 9>(Strategy::Lookup, 5);
+        check::<100, 16>(Strategy::Lookup, 5);
+        check::<1000, 9>(Strategy::Decomposition, 6);
+        check::<{ 1 << 20 }, 6>(Strategy::Decomposition, 6);
+    }
+
+    #[test]
+    fn test_auto_degree_budget() {
+        type Config<const R: usize> = AutoRangeCheckConfig<Fp, R, 256>;
+
+        assert_eq!(Config::<64>::degree(Strategy::Gate), 65);
+        assert_eq!(Config::<64>::select(64), Ok(Strategy::Lookup));
+        assert_eq!(Config::<64>::select(65), Ok(Strategy::Gate));
+
+        // The gate is the only way under degree 5
+        assert_eq!(Config::<3>::select(4), Ok(Strategy::Gate));
+        assert_eq!(Config::<64>::select(4), Err(DegreeError { max_degree: 4, needed: 5 }));
+        assert_eq!(Config::<1000>::select(5), Err(DegreeError { max_degree: 5, needed: 6 }));
+    }
+
+    #[test]
+    fn test_auto_no_strategy() {
+        // k reports the budget before anything is configured
+        let circuit = AutoRangeCircuit::<Fp, 64, 256, 4>::default();
+        assert_eq!(circuit.k(), Err(SizeError::DegreeTooHigh { max_degree: 4, needed: 5 }));
+    }
+}
@@ -0,0 +1,334 @@
+// This helper checks that a value fits in `num_bits` bits, for any `num_bits` up to the field
+// capacity. A lookup into a table of 2^64 rows is out of the question, so the value is split
+// into K-bit words (with LOOKUP_RANGE = 2^K) and every word is looked up in the existing
+// `RangeCheckTable` instead.
+//
+// The words are never witnessed directly. The chip witnesses the running sum
+//
+//    z_0 = v,    z_(i+1) = (z_i - a_i) / 2^K
+//
+// so that a_i = z_i - 2^K * z_(i+1) is the i-th word. After n = ceil(num_bits / K) words the
+// running sum is constrained to zero, which holds exactly when v < 2^(n * K):
+//
+//         z     |   q_lookup   |   q_running
+//  ----------------------------------------------
+//        z_0    |      1       |       1         a_0 = z_0 - 2^K z_1 in table
+//        z_1    |      1       |       1         a_1 = z_1 - 2^K z_2 in table
+//        ...    |              |
+//      z_(n-1)  |      1       |       1         a_(n-1) = z_(n-1) in table
+//        z_n    |              |                 = 0
+//
+// When num_bits is not a multiple of K this only checks v < 2^(n * K). In strict mode the top
+// word a_(n-1), of s = num_bits - (n - 1) * K bits, is checked again after shifting it left by
+// K - s bits: a_(n-1) < 2^s exactly when a_(n-1) * 2^(K - s) < 2^K.
+//
+//         z            |   q_lookup   |   q_running   |   q_short
+//  -------------------------------------------------------------------
+//      a_(n-1)         |              |               |      1
+//      a_(n-1) 2^(K-s) |      1       |       0       |
+//      2^(K-s)         |              |               |              (fixed constant)
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
+
+use super::example2::table::RangeCheckTable;
+use crate::sizing::{self, SizeError};
+
+#[derive(Debug, Clone)]
+pub struct DecompositionConfig<F: FieldExt, const LOOKUP_RANGE: usize> {
+    pub running_sum: Column<Advice>,
+    // Toggles the table lookup
+    pub q_lookup: Selector,
+    // Set on the running sum rows, unset on the shifted word of a strict check
+    pub q_running: Selector,
+    // Toggles the shift of the top word in strict mode
+    pub q_short: Selector,
+    pub table: RangeCheckTable<F, LOOKUP_RANGE>,
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> DecompositionConfig<F, LOOKUP_RANGE> {
+    // Bits per word
+    pub const K: usize = LOOKUP_RANGE.trailing_zeros() as usize;
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        running_sum: Column<Advice>,
+        // Holds the final zero of the running sum and the shift of a strict check
+        constant: Column<Fixed>,
+        // Shared with any other range check using the same table
+        table: RangeCheckTable<F, LOOKUP_RANGE>,
+    ) -> Self {
+        assert!(LOOKUP_RANGE.is_power_of_two() && LOOKUP_RANGE > 1);
+
+        let q_lookup = meta.complex_selector();
+        let q_running = meta.complex_selector();
+        let q_short = meta.selector();
+
+        meta.enable_equality(running_sum);
+        meta.enable_constant(constant);
+
+        // One lookup covers both kinds of rows:
+        //  - q_running = 1: the word z_cur - 2^K * z_next
+        //  - q_running = 0: the shifted top word, witnessed as is
+        meta.lookup(|meta| {
+            let q_lookup = meta.query_selector(q_lookup);
+            let q_running = meta.query_selector(q_running);
+            let z_cur = meta.query_advice(running_sum, Rotation::cur());
+            let z_next = meta.query_advice(running_sum, Rotation::next());
+
+            let word = z_cur.clone() - z_next * F::from(LOOKUP_RANGE as u64);
+            let one = Expression::Constant(F::one());
+            let value = q_running.clone() * word + (one - q_running) * z_cur;
+
+            vec![(q_lookup * value, table.value)]
+        });
+
+        meta.create_gate("short word shift", |meta| {
+            let q_short = meta.query_selector(q_short);
+            let word = meta.query_advice(running_sum, Rotation::cur());
+            let shifted = meta.query_advice(running_sum, Rotation::next());
+            let shift = meta.query_advice(running_sum, Rotation(2));
+
+            Constraints::with_selector(q_short, [("shifted = word * 2^(K - s)", word * shift - shifted)])
+        });
+
+        Self {
+            running_sum,
+            q_lookup,
+            q_running,
+            q_short,
+            table,
+        }
+    }
+
+    // Number of K-bit words a `num_bits` check takes
+    pub fn words(num_bits: usize) -> Result<usize, SizeError> {
+        let max = F::CAPACITY as usize;
+        if num_bits == 0 {
+            return Err(SizeError::NoBits);
+        }
+        if num_bits > max {
+            return Err(SizeError::TooManyBits { bits: num_bits, max });
+        }
+        Ok(num_bits.div_ceil(Self::K))
+    }
+
+    // Rows taken by a check, not counting the lookup table
+    pub fn rows(num_bits: usize, strict: bool) -> Result<usize, SizeError> {
+        let words = Self::words(num_bits)?;
+        let short = strict && !num_bits.is_multiple_of(Self::K);
+        Ok(words + 1 + if short { 3 } else { 0 })
+    }
+
+    // Witnesses `value` and checks that it fits in `num_bits` bits, or in the next multiple of K
+    // bits when `strict` is false. Returns the cell holding the value.
+    pub fn assign(
+        &self,
+        layouter: impl Layouter<F>,
+        value: Value<F>,
+        num_bits: usize,
+        strict: bool,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.decompose(layouter, value, None, num_bits, strict)
+    }
+
+    // Checks that the value in `cell` fits in `num_bits` bits
+    pub fn copy_check(
+        &self,
+        layouter: impl Layouter<F>,
+        cell: &AssignedCell<F, F>,
+        num_bits: usize,
+        strict: bool,
+    ) -> Result<(), Error> {
+        self.decompose(layouter, cell.value().copied(), Some(cell), num_bits, strict)?;
+        Ok(())
+    }
+
+    // Lays out the running sum of `value`, copying z_0 from `copy` when given
+    fn decompose(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+        copy: Option<&AssignedCell<F, F>>,
+        num_bits: usize,
+        strict: bool,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let words = Self::words(num_bits).map_err(|_| Error::Synthesis)?;
+
+        let (value_cell, top_word) = layouter.assign_region(
+            || format!("decompose {} bits", num_bits),
+            |mut region| {
+                let mut z = value;
+                let value_cell = match copy {
+                    Some(cell) => cell.copy_advice(|| "z_0", &mut region, self.running_sum, 0)?,
+                    None => region.assign_advice(|| "z_0", self.running_sum, 0, || z)?,
+                };
+                let mut top_word = value_cell.clone();
+
+                for i in 0..words {
+                    self.q_lookup.enable(&mut region, i)?;
+                    self.q_running.enable(&mut region, i)?;
+
+                    // z_(i+1) = (z_i - a_i) / 2^K, with a_i the low K bits of z_i
+                    let word = z.map(|z| low_bits(&z, Self::K));
+                    let inv = F::from(LOOKUP_RANGE as u64).invert().unwrap();
+                    z = z.zip(word).map(|(z, word)| (z - word) * inv);
+
+                    let cell = region.assign_advice(|| format!("z_{}", i + 1), self.running_sum, i + 1, || z)?;
+                    if i + 1 == words {
+                        region.constrain_constant(cell.cell(), F::zero())?;
+                    } else {
+                        top_word = cell;
+                    }
+                }
+
+                Ok((value_cell, top_word))
+            },
+        )?;
+
+        let short_bits = num_bits % Self::K;
+        if strict && short_bits != 0 {
+            self.short_check(layouter.namespace(|| "top word"), &top_word, short_bits)?;
+        }
+
+        Ok(value_cell)
+    }
+
+    // Checks that `word`, already known to be below 2^K, is below 2^bits
+    fn short_check(&self, mut layouter: impl Layouter<F>, word: &AssignedCell<F, F>, bits: usize) -> Result<(), Error> {
+        let shift = F::from(1 << (Self::K - bits));
+
+        layouter.assign_region(
+            || format!("short check {} bits", bits),
+            |mut region| {
+                self.q_short.enable(&mut region, 0)?;
+                self.q_lookup.enable(&mut region, 1)?;
+
+                let word = word.copy_advice(|| "word", &mut region, self.running_sum, 0)?;
+                region.assign_advice(|| "shifted word", self.running_sum, 1, || word.value().map(|w| *w * shift))?;
+                region.assign_advice_from_constant(|| "2^(K - s)", self.running_sum, 2, shift)?;
+                Ok(())
+            },
+        )
+    }
+}
+
+// The low `bits` bits of a field element, as a field element
+fn low_bits<F: FieldExt>(value: &F, bits: usize) -> F {
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    let word = (0..bits).fold(0u64, |word, i| word | (((bytes[i / 8] >> (i % 8)) & 1) as u64) << i);
+    F::from(word)
+}
+
+// Checks a single value against `num_bits` bits
+#[derive(Default)]
+pub struct DecompositionCircuit<F, const LOOKUP_RANGE: usize> {
+    pub value: Value<F>,
+    pub num_bits: usize,
+    pub strict: bool,
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> DecompositionCircuit<F, LOOKUP_RANGE> {
+    // The lookup table and the running sum share the rows
+    pub fn rows(&self) -> Result<usize, SizeError> {
+        let rows = DecompositionConfig::<F, LOOKUP_RANGE>::rows(self.num_bits, self.strict)?;
+        Ok(rows.max(RangeCheckTable::<F, LOOKUP_RANGE>::ROWS))
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        sizing::minimal_k::<F, Self>(self.rows()?)
+    }
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> Circuit<F> for DecompositionCircuit<F, LOOKUP_RANGE> {
+    type Config = DecompositionConfig<F, LOOKUP_RANGE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            num_bits: self.num_bits,
+            strict: self.strict,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let running_sum = meta.advice_column();
+        let constant = meta.fixed_column();
+        let table = RangeCheckTable::configure(meta);
+        DecompositionConfig::configure(meta, running_sum, constant, table)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        config.table.load(&mut layouter)?;
+        config.assign(layouter.namespace(|| "value"), self.value, self.num_bits, self.strict)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        pasta::{group::ff::PrimeField, Fp},
+    };
+
+    use super::*;
+
+    // 8-bit words
+    const LOOKUP_RANGE: usize = 256;
+
+    fn run(value: Fp, num_bits: usize, strict: bool) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = DecompositionCircuit::<Fp, LOOKUP_RANGE> { value: Value::known(value), num_bits, strict };
+        MockProver::run(circuit.k().unwrap(), &circuit, vec![]).unwrap().verify()
+    }
+
+    #[test]
+    fn test_decomposition_64_bits() {
+        assert_eq!(run(Fp::from(u64::MAX), 64, false), Ok(()));
+        assert_eq!(run(Fp::from(0x0123_4567_89ab_cdef), 64, true), Ok(()));
+        assert_eq!(run(Fp::zero(), 64, false), Ok(()));
+        // 2^64
+        assert!(run(Fp::from(u64::MAX) + Fp::one(), 64, false).is_err());
+        // -1 is the largest field element
+        assert!(run(-Fp::one(), 64, false).is_err());
+    }
+
+    #[test]
+    fn test_decomposition_128_bits() {
+        assert_eq!(run(Fp::from_u128(u128::MAX), 128, false), Ok(()));
+        assert!(run(Fp::from_u128(u128::MAX) + Fp::one(), 128, false).is_err());
+        assert_eq!(run(Fp::from_u128(1 << 100), 101, true), Ok(()));
+        assert!(run(Fp::from_u128(1 << 101), 101, true).is_err());
+    }
+
+    #[test]
+    fn test_decomposition_strict() {
+        // 10 bits take two 8-bit words; the top word has 2 bits
+        assert_eq!(DecompositionConfig::<Fp, LOOKUP_RANGE>::words(10), Ok(2));
+        assert_eq!(run(Fp::from(1023), 10, true), Ok(()));
+        assert!(run(Fp::from(1024), 10, true).is_err());
+
+        // Without strict mode the check rounds up to 16 bits
+        assert_eq!(run(Fp::from(1024), 10, false), Ok(()));
+        assert!(run(Fp::from(1 << 16), 10, false).is_err());
+    }
+
+    #[test]
+    fn test_decomposition_bit_limits() {
+        let max = Fp::CAPACITY as usize;
+        assert_eq!(
+            DecompositionConfig::<Fp, LOOKUP_RANGE>::words(max + 1),
+            Err(SizeError::TooManyBits { bits: max + 1, max })
+        );
+        assert_eq!(run(Fp::from_u128(u128::MAX), max, true), Ok(()));
+    }
+
+    #[test]
+    fn test_decomposition_no_bits() {
+        assert_eq!(DecompositionConfig::<Fp, LOOKUP_RANGE>::words(0), Err(SizeError::NoBits));
+        assert_eq!(DecompositionConfig::<Fp, LOOKUP_RANGE>::rows(0, true), Err(SizeError::NoBits));
+        let circuit = DecompositionCircuit::<Fp, LOOKUP_RANGE> { num_bits: 0, ..Default::default() };
+        assert_eq!(circuit.k(), Err(SizeError::NoBits));
+    }
+}
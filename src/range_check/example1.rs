@@ -9,21 +9,21 @@ use std::marker::PhantomData;
 
 use halo2_proofs::{
     arithmetic::FieldExt, 
-    circuit::*, 
+    circuit::{floor_planner::V1, *}, 
     plonk::*, poly::Rotation
 };
 
 #[derive(Debug, Clone)]
 
 // First we create a config where we have one advice and one selector column and we need the PhantomData for F
-struct RangeCheckConfig<F: FieldExt, const RANGE: usize> {
-    value: Column<Advice>,
-    q_range_check: Selector,
+pub struct RangeCheckConfig<F: FieldExt, const RANGE: usize> {
+    pub value: Column<Advice>,
+    pub q_range_check: Selector,
     _marker: PhantomData<F>
 }
 
 impl<F: FieldExt, const RANGE: usize> RangeCheckConfig<F, RANGE> {
-    fn configure(
+    pub fn configure(
         meta: &mut ConstraintSystem<F>,
         value: Column<Advice>, // It is best practice to pass in advice columns because advice columns are very often shared accross configs
     ) -> Self {
@@ -60,7 +60,7 @@ impl<F: FieldExt, const RANGE: usize> RangeCheckConfig<F, RANGE> {
         config
     }
 
-    fn assign(
+    pub fn assign(
         &self,
         mut layouter: impl Layouter<F>, 
         value: Value<Assigned<F>>
@@ -68,7 +68,7 @@ impl<F: FieldExt, const RANGE: usize> RangeCheckConfig<F, RANGE> {
         layouter.assign_region(|| "Assign value", |mut region| {
             let offset = 0;
             // Enable q_range_check
-            self.q_range_check.enable(&mut region, offset);
+            self.q_range_check.enable(&mut region, offset)?;
 
             // Assign given value
             region.assign_advice(|| "assign value", self.value, offset, || value)?;
@@ -78,50 +78,51 @@ impl<F: FieldExt, const RANGE: usize> RangeCheckConfig<F, RANGE> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use halo2_proofs::{
-        circuit::floor_planner::V1,
-        dev::{FailureLocation, MockProver, VerifyFailure},
-        pasta::Fp,
-        plonk::{Any, Circuit},
-    };
+#[derive(Default)]
+pub struct MyCircuit<F: FieldExt, const RANGE: usize> {
+    pub value: Value<Assigned<F>>,
+}
 
-    use super::*;
+// Example circuit witnessing a single value and range-checking it
+impl<F: FieldExt, const RANGE: usize> Circuit<F> for MyCircuit<F, RANGE> {
+    type Config = RangeCheckConfig<F, RANGE>;
+    type FloorPlanner = V1;
 
-    #[derive(Default)]
-    struct MyCircuit<F: FieldExt, const RANGE: usize> {
-        value: Value<Assigned<F>>,
+    fn without_witnesses(&self) -> Self {
+        Self::default()
     }
 
-    impl<F: FieldExt, const RANGE: usize> Circuit<F> for MyCircuit<F, RANGE> {
-        type Config = RangeCheckConfig<F, RANGE>;
-        type FloorPlanner = V1;
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let value = meta.advice_column();
+        RangeCheckConfig::configure(meta, value)
+    }
 
-        fn without_witnesses(&self) -> Self {
-            Self::default()
-        }
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.assign(layouter.namespace(|| "Assign value"), self.value)?;
 
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let value = meta.advice_column();
-            RangeCheckConfig::configure(meta, value)
-        }
+        Ok(())
+    }
+}
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            config.assign(layouter.namespace(|| "Assign value"), self.value)?;
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        dev::{FailureLocation, MockProver, VerifyFailure},
+        pasta::Fp,
+        plonk::Any,
+    };
 
-            Ok(())
-        }
-    }
+    use super::*;
 
     #[test]
     fn test_range_check_1() {
-        let k = 4;
         const RANGE: usize = 8; // 3-bit value
+        let zero = MyCircuit::<Fp, RANGE> { value: Value::known(Fp::zero().into()) };
+        let k = crate::sizing::search_k(&zero, &[], 10).k.unwrap();
 
         // Successful cases
         for i in 0..RANGE {
@@ -1,34 +1,61 @@
 // This helper checks that the value witnessed in a given cell is  within a given range.
-// Depending on the range, this helper uses either a range-check expression (for small ranges)
-// or a lookup (for larger ranges)
+// Depending on the range, this helper uses either a range-check expression (for RANGE itself)
+// or a lookup (for any other range up to LOOKUP_RANGE)
 //
 //         value   |   q_range_check    |   q_lookup    |   table_value
 //  -------------------------------------------------------------------------
 //           v     |          1         |       0       |       0
 //           v'    |          0         |       1       |       1
-
-use std::marker::PhantomData;
+//
+// The table is tagged with bit widths, and the lookup checks the pair (bits, v). A range R
+// that is not a power of two takes a second lookup: with b = ceil(log2(R)), v < R exactly
+// when both v and v + 2^b - R are below 2^b. The widest tag holds all of 0..2^b, so this
+// also works for a LOOKUP_RANGE that is not a power of two.
+//
+//         value        |   q_lookup    |   q_upper    |   bits   |   shift
+//  ----------------------------------------------------------------------------
+//           v'         |       1       |      1       |    b     |  2^b - R
+//     v' + 2^b - R     |       1       |      0       |    b     |
+//
+// With K the widest tag, the same table also checks v < 2^n for any n <= K, by looking up
+// both v and v * 2^(K - n): the shifted value stays below 2^K only if v < 2^n.
+//
+//         value        |   q_lookup    |   q_short    |   bits   |   shift
+//  ----------------------------------------------------------------------------
+//           v          |       1       |      1       |    K     |  2^(K - n)
+//     v * 2^(K - n)    |       1       |      0       |    K     |
 
 use halo2_proofs::{
     arithmetic::FieldExt, 
-    circuit::*, 
+    circuit::{floor_planner::V1, *}, 
     plonk::*, poly::Rotation
 };
 
-mod table;
-use table::RangeCheckTable;
+pub mod table;
+use table::TaggedRangeCheckTable;
 
 #[derive(Debug, Clone)]
 // First we create a config where we have one advice and one selector column and we need the PhantomData for F
-struct RangeCheckConfig<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
-    value: Column<Advice>,
-    q_range_check: Selector,
-    q_lookup: Selector,
-    table: RangeCheckTable<F, LOOKUP_RANGE>, 
+pub struct RangeCheckConfig<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    pub value: Column<Advice>,
+    pub q_range_check: Selector,
+    pub q_lookup: Selector,
+    // Toggles the shift of a short range check
+    pub q_short: Selector,
+    // Toggles the offset of an exact range check
+    pub q_upper: Selector,
+    // The table tag each lookup uses
+    pub bits: Column<Fixed>,
+    // 2^(K - n) for a short range check against n bits, 2^b - R for an exact one
+    pub shift: Column<Fixed>,
+    pub table: TaggedRangeCheckTable<F, LOOKUP_RANGE>,
 }
 
 impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> RangeCheckConfig<F, RANGE, LOOKUP_RANGE> {
-    fn configure(
+    // Bits covered by the widest tag of the lookup table
+    pub const K: usize = TaggedRangeCheckTable::<F, LOOKUP_RANGE>::BITS;
+
+    pub fn configure(
         meta: &mut ConstraintSystem<F>,
         value: Column<Advice>, // It is best practice to pass in advice columns because advice columns are very often shared accross configs
     ) -> Self {
@@ -38,12 +65,22 @@ impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> RangeCheckConfi
         // Toggles the lookup argument
         let q_lookup = meta.complex_selector(); 
 
+        // Toggle the short and exact range check gates
+        let q_short = meta.selector();
+        let q_upper = meta.selector();
+        let bits = meta.fixed_column();
+        let shift = meta.fixed_column();
+
         // Configure a lookup table
-        let table = RangeCheckTable::configure(meta);
+        let table = TaggedRangeCheckTable::configure(meta);
 
         let config = Self {
             q_range_check,
             q_lookup,
+            q_short,
+            q_upper,
+            bits,
+            shift,
             value,
             table: table.clone(),
         };
@@ -70,32 +107,59 @@ impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> RangeCheckConfi
         });
 
         // Range-check lookup
-        // Check that a value v is contained within a lookup table of values 0..RANGE
+        // Check that a value v is contained within a lookup table of values 0..2^bits
         meta.lookup(|meta| {
             let q_lookup = meta.query_selector(q_lookup);
+            let bits = meta.query_fixed(bits, Rotation::cur());
             let value = meta.query_advice(value, Rotation::cur());
 
             vec![
+                (q_lookup.clone() * bits, table.tag),
                 (q_lookup * value, table.value)
             ]
         });
 
+        // Exact range check
+        // The value on the next row is the current one plus 2^b - R; both rows are looked up
+        // with tag b
+        meta.create_gate("Exact range check", |meta| {
+            let q_upper = meta.query_selector(q_upper);
+            let upper = meta.query_advice(value, Rotation::next());
+            let value = meta.query_advice(value, Rotation::cur());
+            let shift = meta.query_fixed(shift, Rotation::cur());
+
+            Constraints::with_selector(q_upper, [("upper = value + 2^b - R", value + shift - upper)])
+        });
+
+        // Short range check
+        // The value on the next row is the current one shifted left by K - n bits; both rows
+        // are looked up in the table
+        meta.create_gate("Short range check", |meta| {
+            let q_short = meta.query_selector(q_short);
+            let shifted = meta.query_advice(value, Rotation::next());
+            let value = meta.query_advice(value, Rotation::cur());
+            let shift = meta.query_fixed(shift, Rotation::cur());
+
+            Constraints::with_selector(q_short, [("shifted = value * 2^(K - n)", value * shift - shifted)])
+        });
+
         config
     }
 
-    fn assign(
+    pub fn assign(
         &self,
         mut layouter: impl Layouter<F>, 
         value: Value<Assigned<F>>,
         range: usize
     ) -> Result<(), Error> {
-        assert!(range <= LOOKUP_RANGE);
+        assert!(range > 0 && range <= LOOKUP_RANGE);
 
-        if (range < RANGE) {
+        // The gate is fixed to RANGE, any other range goes to the table
+        if range == RANGE {
             layouter.assign_region(|| "Assign value", |mut region| {
                 let offset = 0;
                 // Enable q_range_check
-                self.q_range_check.enable(&mut region, offset);
+                self.q_range_check.enable(&mut region, offset)?;
     
                 // Assign given value
                 region.assign_advice(|| "assign value", self.value, offset, || value)?;
@@ -103,44 +167,125 @@ impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> RangeCheckConfi
                 Ok(())
             })
         } else {
+            // The smallest power of two covering the range
+            let bits = table::tag_bits(range);
+            let offset = (1 << bits) - range;
+
             layouter.assign_region(|| "Assign value for lookup range check", |mut region| {
-                let offset = 0;
-                // Enable q_range_check
-                self.q_lookup.enable(&mut region, offset);
-    
+                let offset_value = Value::known(Assigned::from(F::from(offset as u64)));
+
+                // Enable q_lookup
+                self.q_lookup.enable(&mut region, 0)?;
+                region.assign_fixed(|| "bits", self.bits, 0, || Value::known(F::from(bits as u64)))?;
+
                 // Assign given value
-                region.assign_advice(|| "assign value", self.value, offset, || value)?;
-    
+                region.assign_advice(|| "assign value", self.value, 0, || value)?;
+
+                // Check the upper bound unless the range is a power of two
+                if offset != 0 {
+                    self.q_upper.enable(&mut region, 0)?;
+                    self.q_lookup.enable(&mut region, 1)?;
+                    region.assign_fixed(|| "2^b - R", self.shift, 0, || offset_value)?;
+                    region.assign_fixed(|| "bits", self.bits, 1, || Value::known(F::from(bits as u64)))?;
+                    region.assign_advice(|| "assign upper value", self.value, 1, || value + offset_value)?;
+                }
+
                 Ok(())
             })
         }
         
     }
+
+    // Checks v < 2^num_bits for num_bits <= K with two lookups into the K-bit table
+    pub fn assign_short(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<Assigned<F>>,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        assert!(num_bits <= Self::K);
+        let shift = F::from(1 << (Self::K - num_bits));
+
+        layouter.assign_region(|| format!("Assign value for {}-bit range check", num_bits), |mut region| {
+            self.q_lookup.enable(&mut region, 0)?;
+            self.q_lookup.enable(&mut region, 1)?;
+            self.q_short.enable(&mut region, 0)?;
+
+            region.assign_advice(|| "assign value", self.value, 0, || value)?;
+            region.assign_fixed(|| "2^(K - n)", self.shift, 0, || Value::known(shift))?;
+            for offset in 0..2 {
+                region.assign_fixed(|| "bits", self.bits, offset, || Value::known(F::from(Self::K as u64)))?;
+            }
+            region.assign_advice(|| "assign shifted value", self.value, 1, || value * Value::known(Assigned::from(shift)))?;
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct MyCircuit<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    pub value: Value<Assigned<F>>,
+    pub large_value: Value<Assigned<F>>,
+}
+
+// Example circuit checking a small value with the gate and a larger value with the lookup
+impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> Circuit<F> for MyCircuit<F, RANGE, LOOKUP_RANGE> {
+    type Config = RangeCheckConfig<F, RANGE, LOOKUP_RANGE>;
+    type FloorPlanner = V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let value = meta.advice_column();
+        RangeCheckConfig::configure(meta, value)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.table.load(&mut layouter)?;
+        config.assign(layouter.namespace(|| "Assign value"), self.value, RANGE)?;
+        config.assign(layouter.namespace(|| "Assign larger value"), self.large_value, LOOKUP_RANGE)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use halo2_proofs::{
-        circuit::floor_planner::V1,
         dev::{FailureLocation, MockProver, VerifyFailure},
         pasta::Fp,
-        plonk::{Any, Circuit},
     };
 
     use super::*;
 
-    #[derive(Default)]
-    struct MyCircuit<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> {
+    // The check a `CheckCircuit` makes
+    #[derive(Clone, Copy)]
+    enum Check {
+        // `assign` against a range
+        Range(usize),
+        // `assign_short` against a number of bits
+        Short(usize),
+    }
+
+    // Checks a single value with one of the lookups
+    struct CheckCircuit<F: FieldExt, const LOOKUP_RANGE: usize> {
         value: Value<Assigned<F>>,
-        large_value: Value<Assigned<F>>,
+        check: Check,
     }
 
-    impl<F: FieldExt, const RANGE: usize, const LOOKUP_RANGE: usize> Circuit<F> for MyCircuit<F, RANGE, LOOKUP_RANGE> {
-        type Config = RangeCheckConfig<F, RANGE, LOOKUP_RANGE>;
+    impl<F: FieldExt, const LOOKUP_RANGE: usize> Circuit<F> for CheckCircuit<F, LOOKUP_RANGE> {
+        type Config = RangeCheckConfig<F, 8, LOOKUP_RANGE>;
         type FloorPlanner = V1;
 
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self { value: Value::unknown(), check: self.check }
         }
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
@@ -148,24 +293,79 @@ mod tests {
             RangeCheckConfig::configure(meta, value)
         }
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
             config.table.load(&mut layouter)?;
-            config.assign(layouter.namespace(|| "Assign value"), self.value, RANGE)?;
-            config.assign(layouter.namespace(|| "Assign larger value"), self.large_value, LOOKUP_RANGE)?;
+            match self.check {
+                Check::Range(range) => config.assign(layouter.namespace(|| "Assign value"), self.value, range),
+                Check::Short(num_bits) => {
+                    config.assign_short(layouter.namespace(|| "Assign short value"), self.value, num_bits)
+                }
+            }
+        }
+    }
 
-            Ok(())
+    #[test]
+    fn test_exact_range_check() {
+        const LOOKUP_RANGE: usize = 256; // 8-bit table
+        let circuit = |value: u64, range: usize| CheckCircuit::<Fp, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(value).into()),
+            check: Check::Range(range),
+        };
+        let k = crate::sizing::search_k(&circuit(0, 100), &[], 12).k.unwrap();
+
+        // Ranges that are not a power of two take the second lookup
+        for (range, max) in [(100, 99), (200, 199), (9, 8), (255, 254)] {
+            MockProver::run(k, &circuit(max, range), vec![]).unwrap().assert_satisfied();
+            assert!(MockProver::run(k, &circuit(max + 1, range), vec![]).unwrap().verify().is_err());
+        }
+
+        // Powers of two take one
+        for range in [1, 16, 128, 256] {
+            let max = range as u64 - 1;
+            MockProver::run(k, &circuit(max, range), vec![]).unwrap().assert_satisfied();
+            assert!(MockProver::run(k, &circuit(max + 1, range), vec![]).unwrap().verify().is_err());
         }
+
+        // -1 wraps the upper value back into the table
+        let circuit = CheckCircuit::<Fp, LOOKUP_RANGE> {
+            value: Value::known((-Fp::one()).into()),
+            check: Check::Range(100),
+        };
+        assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_short_range_check() {
+        const LOOKUP_RANGE: usize = 256; // 8-bit table
+        let circuit = |value: u64, num_bits: usize| CheckCircuit::<Fp, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(value).into()),
+            check: Check::Short(num_bits),
+        };
+        let k = crate::sizing::search_k(&circuit(0, 3), &[], 12).k.unwrap();
+
+        // Every 3-bit value passes, 8 does not
+        for i in 0..8 {
+            MockProver::run(k, &circuit(i, 3), vec![]).unwrap().assert_satisfied();
+        }
+        assert!(MockProver::run(k, &circuit(8, 3), vec![]).unwrap().verify().is_err());
+
+        // The bound holds at both ends: 0 bits only admits 0, K bits is the plain lookup
+        MockProver::run(k, &circuit(0, 0), vec![]).unwrap().assert_satisfied();
+        assert!(MockProver::run(k, &circuit(1, 0), vec![]).unwrap().verify().is_err());
+        MockProver::run(k, &circuit(255, 8), vec![]).unwrap().assert_satisfied();
+        assert!(MockProver::run(k, &circuit(256, 8), vec![]).unwrap().verify().is_err());
     }
 
     #[test]
     fn test_range_check_1() {
-        let k = 9;
         const RANGE: usize = 8; // 3-bit value
         const LOOKUP_RANGE: usize = 256; // 8-bit value
+        // The lookup table needs 256 rows
+        let zero = MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
+            value: Value::known(Fp::zero().into()),
+            large_value: Value::known(Fp::zero().into()),
+        };
+        let k = crate::sizing::search_k(&zero, &[], 12).k.unwrap();
 
         // Successful cases
         for i in 0..RANGE {
@@ -179,22 +379,57 @@ mod tests {
         }
 
         // Out-of-range value, v=8
-        // let circuit = MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
-        //     value: Value::known(Fp::from(RANGE as u64).into()),
-        // };
-
-        // let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-        // // Unit test expecting the faliure when the value is out of range
-        // assert_eq!(
-        //     prover.verify(),
-        //     Err(vec![VerifyFailure::ConstraintNotSatisfied {
-        //         constraint: ((0, "Range check").into(), 0, "range check").into(),
-        //         location: FailureLocation::InRegion {
-        //             region: (0, "Assign value").into(),
-        //             offset: 0
-        //         },
-        //         cell_values: vec![(((Any::Advice, 0).into(), 0).into(), "0x8".to_string())]
-        //     }])
-        // );
+        let circuit = MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(RANGE as u64).into()),
+            large_value: Value::known(Fp::zero().into()),
+        };
+
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        // Unit test expecting the faliure when the value is out of range
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::ConstraintNotSatisfied {
+                constraint: ((0, "Range check").into(), 0, "range check").into(),
+                location: FailureLocation::InRegion {
+                    region: (1, "Assign value").into(),
+                    offset: 0
+                },
+                cell_values: vec![(((Any::Advice, 0).into(), 0).into(), "0x8".to_string())]
+            }])
+        );
+
+        // A large value within the table but outside its bound of 100
+        let circuit = CheckCircuit::<Fp, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(200).into()),
+            check: Check::Range(100),
+        };
+        assert!(MockProver::run(k, &circuit, vec![]).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn test_range_check_non_power_of_two() {
+        const RANGE: usize = 8; // 3-bit value
+        const LOOKUP_RANGE: usize = 200; // The widest tag covers 0..256
+        let circuit = |large_value: u64| MyCircuit::<Fp, RANGE, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(7).into()),
+            large_value: Value::known(Fp::from(large_value).into()),
+        };
+        let k = crate::sizing::search_k(&circuit(0), &[], 12).k.unwrap();
+        assert_eq!(k, 10);
+
+        for large_value in [0, 150, 199] {
+            MockProver::run(k, &circuit(large_value), vec![]).unwrap().assert_satisfied();
+        }
+        for large_value in [200, 255, 256] {
+            assert!(MockProver::run(k, &circuit(large_value), vec![]).unwrap().verify().is_err());
+        }
+
+        // The short check reaches the widest tag
+        let circuit = |value: u64| CheckCircuit::<Fp, LOOKUP_RANGE> {
+            value: Value::known(Fp::from(value).into()),
+            check: Check::Short(8),
+        };
+        MockProver::run(k, &circuit(255), vec![]).unwrap().assert_satisfied();
+        assert!(MockProver::run(k, &circuit(256), vec![]).unwrap().verify().is_err());
     }
-}
\ No newline at end of file
+}
@@ -8,13 +8,16 @@ use halo2_proofs::{
 
 /// A lookup table of values from 0..RANGE.
 #[derive(Debug, Clone)]
-pub(super) struct RangeCheckTable<F: FieldExt, const RANGE: usize> {
-    pub(super) value: TableColumn,
+pub struct RangeCheckTable<F: FieldExt, const RANGE: usize> {
+    pub value: TableColumn,
     _marker: PhantomData<F>,
 }
 
 impl<F: FieldExt, const RANGE: usize> RangeCheckTable<F, RANGE> {
-    pub(super) fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+    /// Rows the table occupies.
+    pub const ROWS: usize = RANGE;
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
         let value = meta.lookup_table_column();
 
         Self {
@@ -23,19 +26,17 @@ impl<F: FieldExt, const RANGE: usize> RangeCheckTable<F, RANGE> {
         }
     }
 
-    pub(super) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         layouter.assign_table(
             || "load range-check table",
             |mut table| {
-                let mut offset = 0;
-                for value in 0..RANGE {
+                for (offset, value) in (0..RANGE).enumerate() {
                     table.assign_cell(
                         || "num_bits",
                         self.value,
                         offset,
                         || Value::known(F::from(value as u64)),
                     )?;
-                    offset += 1;
                 }
 
                 Ok(())
@@ -43,3 +44,80 @@ impl<F: FieldExt, const RANGE: usize> RangeCheckTable<F, RANGE> {
         )
     }
 }
+
+/// A lookup table of values tagged with a bit width.
+///
+/// For every width `bits` up to `BITS = ceil(log2(RANGE))` the table holds the pairs
+/// `(bits, v)` for `v < 2^bits`, so a two-column lookup checks a value against any power of
+/// two up to `2^BITS`. When RANGE is not a power of two the widest tag runs past RANGE, so
+/// only a lookup together with an upper bound check (see `RangeCheckConfig::assign`) checks
+/// `v < RANGE`.
+///
+/// ```text
+///   tag  |  value
+/// ----------------
+///    0   |    0
+///    1   |    0
+///    1   |    1
+///    2   |    0
+///   ...  |   ...
+///  BITS  | 2^BITS - 1
+/// ```
+#[derive(Debug, Clone)]
+pub struct TaggedRangeCheckTable<F: FieldExt, const RANGE: usize> {
+    pub tag: TableColumn,
+    pub value: TableColumn,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const RANGE: usize> TaggedRangeCheckTable<F, RANGE> {
+    /// The widest tag.
+    pub const BITS: usize = tag_bits(RANGE);
+
+    /// Rows the table occupies.
+    pub const ROWS: usize = table_rows(RANGE);
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let tag = meta.lookup_table_column();
+        let value = meta.lookup_table_column();
+
+        Self {
+            tag,
+            value,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "load tagged range-check table",
+            |mut table| {
+                let mut offset = 0;
+                for bits in 0..=Self::BITS {
+                    for value in 0..1 << bits {
+                        table.assign_cell(|| "tag", self.tag, offset, || Value::known(F::from(bits as u64)))?;
+                        table.assign_cell(
+                            || "num_bits",
+                            self.value,
+                            offset,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                        offset += 1;
+                    }
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+/// The smallest width covering 0..range.
+pub const fn tag_bits(range: usize) -> usize {
+    (usize::BITS - (range - 1).leading_zeros()) as usize
+}
+
+/// Rows a tagged table of 0..range occupies, 2^(b + 1) - 1 for b = ceil(log2(range)).
+pub const fn table_rows(range: usize) -> usize {
+    (1 << (tag_bits(range) + 1)) - 1
+}
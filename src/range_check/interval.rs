@@ -0,0 +1,358 @@
+// This helper checks that a value lies in an interval lo <= v < hi, where the bounds are not
+// limited to [0, RANGE) like the expression gate of example1 and example2.
+//
+// The bounds are copied next to the value, and the gate witnesses the two distances
+//
+//    a = v - lo,    b = hi - 1 - v
+//
+//         value      |   q_interval
+//  ----------------------------------
+//           v        |       1
+//           lo       |                 copied from a constant, a fixed cell or an instance
+//           hi       |                 copied from a constant, a fixed cell or an instance
+//         v - lo     |
+//       hi - 1 - v   |
+//
+// Both distances are then range-checked to num_bits bits with the decomposition chip. In the
+// field a and b wrap around when v is out of the interval, so they can not both be small:
+// a + b = hi - 1 - lo, and with a, b < 2^num_bits < p / 2 the sum can not wrap, which leaves
+// lo <= v <= hi - 1 as integers. The interval itself only has to satisfy hi - lo <= 2^num_bits
+// for the valid values to pass.
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::*, plonk::*, poly::Rotation};
+
+use super::{decomposition::DecompositionConfig, example2::table::RangeCheckTable};
+use crate::sizing::{self, SizeError};
+
+// Where a bound of the interval comes from
+#[derive(Debug, Clone)]
+pub enum Bound<F: FieldExt> {
+    // Fixed in the circuit through the constant column
+    Constant(F),
+    // A cell assigned elsewhere in the circuit, usually in a fixed column; its column needs
+    // equality enabled
+    Fixed(AssignedCell<F, F>),
+    // A row of an instance column with equality enabled
+    Instance(Column<Instance>, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct IntervalConfig<F: FieldExt, const LOOKUP_RANGE: usize> {
+    pub value: Column<Advice>,
+    pub q_interval: Selector,
+    pub decomposition: DecompositionConfig<F, LOOKUP_RANGE>,
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> IntervalConfig<F, LOOKUP_RANGE> {
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        // Checks the two distances; its constant column also takes the constant bounds
+        decomposition: DecompositionConfig<F, LOOKUP_RANGE>,
+    ) -> Self {
+        let q_interval = meta.selector();
+        meta.enable_equality(value);
+
+        meta.create_gate("interval", |meta| {
+            let q_interval = meta.query_selector(q_interval);
+            let v = meta.query_advice(value, Rotation::cur());
+            let lo = meta.query_advice(value, Rotation::next());
+            let hi = meta.query_advice(value, Rotation(2));
+            let a = meta.query_advice(value, Rotation(3));
+            let b = meta.query_advice(value, Rotation(4));
+            let one = Expression::Constant(F::one());
+
+            Constraints::with_selector(
+                q_interval,
+                [("a = v - lo", a - (v.clone() - lo)), ("b = hi - 1 - v", b - (hi - one - v))],
+            )
+        });
+
+        Self {
+            value,
+            q_interval,
+            decomposition,
+        }
+    }
+
+    // Bits a check takes for hi - lo values, at least one
+    pub fn width_bits(lo: u64, hi: u64) -> Result<usize, SizeError> {
+        if lo >= hi {
+            return Err(SizeError::EmptyInterval { lo, hi });
+        }
+        Ok(((u64::BITS - (hi - lo - 1).leading_zeros()) as usize).max(1))
+    }
+
+    // Rows taken by a check, not counting the lookup table and the constants
+    pub fn rows(num_bits: usize) -> Result<usize, SizeError> {
+        Self::check_bits(num_bits)?;
+        Ok(5 + 2 * DecompositionConfig::<F, LOOKUP_RANGE>::rows(num_bits, true)?)
+    }
+
+    // The distances have to stay below p / 2
+    fn check_bits(num_bits: usize) -> Result<(), SizeError> {
+        let max = F::CAPACITY as usize - 1;
+        if num_bits > max {
+            return Err(SizeError::TooManyBits { bits: num_bits, max });
+        }
+        Ok(())
+    }
+
+    // Witnesses `value` and checks lo <= value < hi, for an interval of at most 2^num_bits
+    // values. Returns the cell holding the value.
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+        lo: &Bound<F>,
+        hi: &Bound<F>,
+        num_bits: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        Self::check_bits(num_bits).map_err(|_| Error::Synthesis)?;
+
+        let (value_cell, a, b) = layouter.assign_region(
+            || "interval",
+            |mut region| {
+                self.q_interval.enable(&mut region, 0)?;
+
+                let v = region.assign_advice(|| "v", self.value, 0, || value)?;
+                let lo = self.load_bound(&mut region, "lo", lo, 1)?;
+                let hi = self.load_bound(&mut region, "hi", hi, 2)?;
+
+                let a = v.value().zip(lo.value()).map(|(v, lo)| *v - lo);
+                let a = region.assign_advice(|| "v - lo", self.value, 3, || a)?;
+                let b = v.value().zip(hi.value()).map(|(v, hi)| *hi - F::one() - v);
+                let b = region.assign_advice(|| "hi - 1 - v", self.value, 4, || b)?;
+
+                Ok((v, a, b))
+            },
+        )?;
+
+        self.decomposition.copy_check(layouter.namespace(|| "v - lo"), &a, num_bits, true)?;
+        self.decomposition.copy_check(layouter.namespace(|| "hi - 1 - v"), &b, num_bits, true)?;
+
+        Ok(value_cell)
+    }
+
+    fn load_bound(
+        &self,
+        region: &mut Region<'_, F>,
+        name: &str,
+        bound: &Bound<F>,
+        offset: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        match bound {
+            Bound::Constant(c) => region.assign_advice_from_constant(|| name, self.value, offset, *c),
+            Bound::Fixed(cell) => cell.copy_advice(|| name, region, self.value, offset),
+            Bound::Instance(column, row) => {
+                region.assign_advice_from_instance(|| name, *column, *row, self.value, offset)
+            }
+        }
+    }
+}
+
+// How `IntervalCircuit` provides a bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoundKind {
+    #[default]
+    Constant,
+    Fixed,
+    Instance,
+}
+
+#[derive(Debug, Clone)]
+pub struct IntervalCircuitConfig<F: FieldExt, const LOOKUP_RANGE: usize> {
+    pub interval: IntervalConfig<F, LOOKUP_RANGE>,
+    // Holds the fixed bounds
+    pub bounds: Column<Fixed>,
+    // Holds the public bounds, lo first
+    pub instance: Column<Instance>,
+}
+
+// Checks a single value against [lo, hi)
+#[derive(Default)]
+pub struct IntervalCircuit<F, const LOOKUP_RANGE: usize> {
+    pub value: Value<F>,
+    pub lo: u64,
+    pub hi: u64,
+    pub lo_kind: BoundKind,
+    pub hi_kind: BoundKind,
+    pub num_bits: usize,
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> IntervalCircuit<F, LOOKUP_RANGE> {
+    // Fails if no value lies in [lo, hi)
+    pub fn new(value: u64, lo: u64, hi: u64, lo_kind: BoundKind, hi_kind: BoundKind) -> Result<Self, SizeError> {
+        Ok(Self {
+            value: Value::known(F::from(value)),
+            lo,
+            hi,
+            lo_kind,
+            hi_kind,
+            num_bits: IntervalConfig::<F, LOOKUP_RANGE>::width_bits(lo, hi)?,
+        })
+    }
+
+    // The public bounds, in the order the instance column holds them
+    pub fn public_inputs(&self) -> Vec<F> {
+        [(self.lo_kind, self.lo), (self.hi_kind, self.hi)]
+            .into_iter()
+            .filter(|(kind, _)| *kind == BoundKind::Instance)
+            .map(|(_, bound)| F::from(bound))
+            .collect()
+    }
+
+    // The lookup table and the check share the rows
+    pub fn rows(&self) -> Result<usize, SizeError> {
+        let rows = IntervalConfig::<F, LOOKUP_RANGE>::rows(self.num_bits)?;
+        Ok(rows.max(RangeCheckTable::<F, LOOKUP_RANGE>::ROWS))
+    }
+
+    pub fn k(&self) -> Result<u32, SizeError> {
+        sizing::minimal_k::<F, Self>(self.rows()?)
+    }
+}
+
+impl<F: FieldExt, const LOOKUP_RANGE: usize> Circuit<F> for IntervalCircuit<F, LOOKUP_RANGE> {
+    type Config = IntervalCircuitConfig<F, LOOKUP_RANGE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            value: Value::unknown(),
+            ..*self
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let value = meta.advice_column();
+        let running_sum = meta.advice_column();
+        let constant = meta.fixed_column();
+        let bounds = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(bounds);
+        meta.enable_equality(instance);
+
+        let table = RangeCheckTable::configure(meta);
+        let decomposition = DecompositionConfig::configure(meta, running_sum, constant, table);
+
+        IntervalCircuitConfig {
+            interval: IntervalConfig::configure(meta, value, decomposition),
+            bounds,
+            instance,
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        config.interval.decomposition.table.load(&mut layouter)?;
+
+        let mut instance_row = 0;
+        let mut bounds = vec![];
+        for (kind, bound) in [(self.lo_kind, self.lo), (self.hi_kind, self.hi)] {
+            let bound = F::from(bound);
+            bounds.push(match kind {
+                BoundKind::Constant => Bound::Constant(bound),
+                BoundKind::Fixed => Bound::Fixed(layouter.assign_region(
+                    || "fixed bound",
+                    |mut region| region.assign_fixed(|| "bound", config.bounds, 0, || Value::known(bound)),
+                )?),
+                BoundKind::Instance => {
+                    instance_row += 1;
+                    Bound::Instance(config.instance, instance_row - 1)
+                }
+            });
+        }
+
+        config
+            .interval
+            .assign(layouter.namespace(|| "value"), self.value, &bounds[0], &bounds[1], self.num_bits)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        pasta::{group::ff::PrimeField, Fp},
+    };
+
+    use super::*;
+
+    // 8-bit words
+    const LOOKUP_RANGE: usize = 256;
+
+    fn run(circuit: IntervalCircuit<Fp, LOOKUP_RANGE>, instance: Vec<Fp>) -> Result<(), Vec<VerifyFailure>> {
+        MockProver::run(circuit.k().unwrap(), &circuit, vec![instance]).unwrap().verify()
+    }
+
+    #[test]
+    fn test_interval_constant_bounds() {
+        use BoundKind::Constant;
+
+        // Age between 18 and 120
+        let age = |v| IntervalCircuit::new(v, 18, 121, Constant, Constant).unwrap();
+        assert_eq!(age(0).num_bits, 7);
+        for v in [18, 50, 120] {
+            assert_eq!(run(age(v), vec![]), Ok(()));
+        }
+        for v in [0, 17, 121, 255] {
+            assert!(run(age(v), vec![]).is_err());
+        }
+
+        // A single value
+        assert_eq!(run(IntervalCircuit::new(7, 7, 8, Constant, Constant).unwrap(), vec![]), Ok(()));
+        assert!(run(IntervalCircuit::new(8, 7, 8, Constant, Constant).unwrap(), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_interval_fixed_and_instance_bounds() {
+        use BoundKind::{Constant, Fixed, Instance};
+
+        let circuit = |v| IntervalCircuit::new(v, 1000, 1 << 20, Fixed, Instance).unwrap();
+        assert_eq!(circuit(0).public_inputs(), vec![Fp::from(1 << 20)]);
+        assert_eq!(run(circuit(1000), circuit(0).public_inputs()), Ok(()));
+        assert_eq!(run(circuit((1 << 20) - 1), circuit(0).public_inputs()), Ok(()));
+        assert!(run(circuit(999), circuit(0).public_inputs()).is_err());
+        assert!(run(circuit(1 << 20), circuit(0).public_inputs()).is_err());
+
+        // The verifier's bound is the one enforced
+        assert!(run(circuit(5000), vec![Fp::from(5000)]).is_err());
+        assert_eq!(run(circuit(4999), vec![Fp::from(5000)]), Ok(()));
+
+        // Both bounds public
+        let circuit = IntervalCircuit::<Fp, LOOKUP_RANGE>::new(30, 18, 121, Instance, Instance).unwrap();
+        assert_eq!(run(circuit, vec![Fp::from(18), Fp::from(121)]), Ok(()));
+        let circuit = IntervalCircuit::<Fp, LOOKUP_RANGE>::new(30, 18, 121, Instance, Instance).unwrap();
+        assert!(run(circuit, vec![Fp::from(31), Fp::from(121)]).is_err());
+
+        // The top of the u64 range
+        let circuit = IntervalCircuit::<Fp, LOOKUP_RANGE>::new(u64::MAX, u64::MAX - 1, u64::MAX, Constant, Fixed).unwrap();
+        assert!(run(circuit, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_interval_bit_limits() {
+        let max = Fp::CAPACITY as usize - 1;
+        assert_eq!(
+            IntervalConfig::<Fp, LOOKUP_RANGE>::rows(max + 1),
+            Err(SizeError::TooManyBits { bits: max + 1, max })
+        );
+        assert!(IntervalConfig::<Fp, LOOKUP_RANGE>::rows(max).is_ok());
+        assert_eq!(IntervalConfig::<Fp, LOOKUP_RANGE>::width_bits(0, 1), Ok(1));
+        assert_eq!(IntervalConfig::<Fp, LOOKUP_RANGE>::width_bits(0, 256), Ok(8));
+        assert_eq!(IntervalConfig::<Fp, LOOKUP_RANGE>::width_bits(0, 257), Ok(9));
+    }
+
+    #[test]
+    fn test_interval_empty() {
+        use BoundKind::Constant;
+
+        for (lo, hi) in [(7, 7), (8, 7), (u64::MAX, 0)] {
+            assert_eq!(
+                IntervalCircuit::<Fp, LOOKUP_RANGE>::new(7, lo, hi, Constant, Constant).err(),
+                Some(SizeError::EmptyInterval { lo, hi })
+            );
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// Helpers to pick the circuit size `k` from the number of rows a circuit needs.
+//
+// A circuit with 2^k rows cannot use all of them: halo2 reserves the last
+// `blinding_factors() + 1` rows for blinding the witness polynomials, so the
+// usable area is smaller than the table itself.
+//
+//   row 0           |  usable rows
+//   ...             |
+//   2^k - m - 2     |
+//   2^k - m - 1     |  l_last
+//   2^k - m .. 2^k  |  m blinding rows
+//
+// `minimal_k` works from a row count the circuit reports about itself. `search_k` needs
+// nothing but the circuit and its instances: it runs the MockProver for growing k and
+// records why every smaller k was turned down.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::MockProver,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+
+use crate::planner;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeError {
+    // The circuit was asked to lay out an empty sequence
+    NoSteps,
+    // The circuit needs more rows than the largest supported domain provides
+    TooManyRows { rows: usize, max_k: u32 },
+    // A value was asked to be decomposed into more bits than the circuit supports
+    TooManyBits { bits: usize, max: usize },
+    // A value was asked to be decomposed into zero bits
+    NoBits,
+    // No way of laying out the circuit stays within its degree budget
+    DegreeTooHigh { max_degree: usize, needed: usize },
+    // An interval [lo, hi) with no value in it
+    EmptyInterval { lo: u64, hi: u64 },
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeError::NoSteps => write!(f, "the circuit needs at least one step"),
+            SizeError::TooManyRows { rows, max_k } => write!(
+                f,
+                "{} rows do not fit in the largest supported circuit (k = {})",
+                rows, max_k
+            ),
+            SizeError::TooManyBits { bits, max } => {
+                write!(f, "{} bits requested, the circuit supports at most {}", bits, max)
+            }
+            SizeError::NoBits => write!(f, "the decomposition needs at least one bit"),
+            SizeError::DegreeTooHigh { max_degree, needed } => {
+                write!(f, "the circuit needs degree {}, its budget is {}", needed, max_degree)
+            }
+            SizeError::EmptyInterval { lo, hi } => write!(f, "the interval [{}, {}) is empty", lo, hi),
+        }
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+// Largest k `Params::new` accepts; it asserts k < 32
+pub const PARAMS_MAX_K: u32 = 31;
+
+// Returns the largest k circuit `C` can be proven at
+pub fn max_k<F: FieldExt, C: Circuit<F>>() -> u32 {
+    let mut meta = ConstraintSystem::<F>::default();
+    C::configure(&mut meta);
+    max_k_for(&meta)
+}
+
+fn max_k_for<F: FieldExt>(meta: &ConstraintSystem<F>) -> u32 {
+    // The prover evaluates the quotient polynomial on an extended domain of size
+    // 2^k * 2^ceil(log2(degree - 1)), which has to fit in the 2-adic subgroup of F.
+    let quotient_degree = meta.degree().saturating_sub(1).max(1);
+    let extension = usize::BITS - (quotient_degree - 1).leading_zeros();
+    (F::S - extension).min(PARAMS_MAX_K)
+}
+
+// Returns the smallest `k` such that `rows` rows (and the instance column, which is
+// laid out over the same rows) fit in the usable area of a 2^k table for circuit `C`.
+pub fn minimal_k<F: FieldExt, C: Circuit<F>>(rows: usize) -> Result<u32, SizeError> {
+    let mut meta = ConstraintSystem::<F>::default();
+    C::configure(&mut meta);
+    let max_k = max_k_for(&meta);
+
+    let required = (rows + meta.blinding_factors() + 1).max(meta.minimum_rows());
+
+    (0..=max_k)
+        .find(|k| required <= 1usize << k)
+        .ok_or(SizeError::TooManyRows { rows, max_k })
+}
+
+// Why the MockProver turned down a given k
+#[derive(Debug)]
+pub enum Rejection {
+    // The layout, lookup tables included, does not fit above the blinding rows.
+    // `needed` is None when the layout could not be measured.
+    NotEnoughRows { usable: usize, needed: Option<usize> },
+    // The instance column is longer than the usable rows
+    InstanceTooLarge { usable: usize, len: usize },
+    // Synthesis failed for a reason a larger k does not fix
+    Synthesis(Error),
+    // The circuit fits but its constraints, lookups or copies fail
+    Unsatisfied(Vec<String>),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::NotEnoughRows { usable, needed: Some(needed) } => {
+                write!(f, "{} usable rows, the layout needs {}", usable, needed)
+            }
+            Rejection::NotEnoughRows { usable, needed: None } => write!(f, "{} usable rows are not enough", usable),
+            Rejection::InstanceTooLarge { usable, len } => {
+                write!(f, "{} usable rows, the instance column has {} values", usable, len)
+            }
+            Rejection::Synthesis(error) => write!(f, "synthesis failed: {:?}", error),
+            Rejection::Unsatisfied(failures) => write!(f, "not satisfied: {}", failures.join("; ")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct KSearch {
+    // Smallest accepted k, None if no k up to the limit was accepted
+    pub k: Option<u32>,
+    // Every k that was tried and turned down, in increasing order
+    pub rejected: Vec<(u32, Rejection)>,
+}
+
+// Runs the MockProver on `circuit` for k = 1, 2, ... up to `max_k` and stops at the first k it
+// accepts. Running out of rows or instance space moves on to the next k; any other failure
+// does not depend on k, so the search ends there without an answer.
+pub fn search_k<F: FieldExt, C: Circuit<F>>(circuit: &C, instances: &[Vec<F>], max_k: u32) -> KSearch {
+    let mut meta = ConstraintSystem::<F>::default();
+    C::configure(&mut meta);
+    let unusable = meta.blinding_factors() + 1;
+    let needed = planner::rows_used::<F, C, C::FloorPlanner>(circuit).ok();
+    let len = instances.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut rejected = vec![];
+    for k in 1..=max_k {
+        let usable = (1usize << k).saturating_sub(unusable);
+        let rejection = match MockProver::run(k, circuit, instances.to_vec()) {
+            Ok(prover) => match prover.verify() {
+                Ok(()) => return KSearch { k: Some(k), rejected },
+                Err(failures) => Rejection::Unsatisfied(failures.iter().map(|f| f.to_string()).collect()),
+            },
+            Err(Error::NotEnoughRowsAvailable { .. }) => Rejection::NotEnoughRows { usable, needed },
+            Err(Error::InstanceTooLarge) => Rejection::InstanceTooLarge { usable, len },
+            Err(error) => Rejection::Synthesis(error),
+        };
+
+        let retry = matches!(rejection, Rejection::NotEnoughRows { .. } | Rejection::InstanceTooLarge { .. });
+        rejected.push((k, rejection));
+        if !retry {
+            break;
+        }
+    }
+    KSearch { k: None, rejected }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        pasta::Fp,
+        plonk::{Advice, Column, Selector},
+        poly::Rotation,
+    };
+
+    use super::*;
+    use crate::{
+        fibonacci::{PublicInputs, ThreeColumnFiboCircuit},
+        instance::InstanceColumn,
+        range_check,
+    };
+
+    // A single degree-2 gate, s * a = 0, and nothing to copy
+    #[derive(Default)]
+    struct Degree2;
+
+    impl Circuit<Fp> for Degree2 {
+        type Config = (Column<Advice>, Selector);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let s = meta.selector();
+            meta.create_gate("s * a", |meta| vec![meta.query_selector(s) * meta.query_advice(a, Rotation::cur())]);
+            (a, s)
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_minimal_k_degree_2() {
+        // The gate alone would leave room for k = S = 32, which Params::new rejects. halo2
+        // reports at least degree 3 for the permutation argument, and either way the largest
+        // k is 31.
+        let mut meta = ConstraintSystem::<Fp>::default();
+        Degree2::configure(&mut meta);
+        assert!(meta.degree() <= 3);
+        assert_eq!(max_k::<Fp, Degree2>(), PARAMS_MAX_K);
+
+        let usable = (1usize << PARAMS_MAX_K) - meta.blinding_factors() - 1;
+        assert_eq!(minimal_k::<Fp, Degree2>(usable), Ok(PARAMS_MAX_K));
+        assert_eq!(
+            minimal_k::<Fp, Degree2>(usable + 1),
+            Err(SizeError::TooManyRows { rows: usable + 1, max_k: PARAMS_MAX_K })
+        );
+    }
+
+    #[test]
+    fn test_search_k_matches_minimal_k() {
+        let circuit = ThreeColumnFiboCircuit { a: Value::known(Fp::one()), b: Value::known(Fp::one()), steps: 8 };
+        let search = search_k(&circuit, &PublicInputs::new(Fp::one(), Fp::one(), 8).instances(), 10);
+
+        assert_eq!(search.k, Some(circuit.k().unwrap()));
+        // k = 3 leaves 2 of its 8 rows usable, short of both the 8 rows of the
+        // layout and the 3 public inputs; the instance is checked first
+        assert!(matches!(
+            search.rejected[..],
+            [
+                (1, Rejection::NotEnoughRows { needed: Some(8), .. }),
+                (2, Rejection::NotEnoughRows { needed: Some(8), .. }),
+                (3, Rejection::InstanceTooLarge { usable: 2, len: 3 }),
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_search_k_lookup_table() {
+        // 511 tagged table rows do not fit above the blinding rows of a 2^9 table
+        let circuit = range_check::example2::MyCircuit::<Fp, 8, 256> {
+            value: Value::known(Fp::from(7).into()),
+            large_value: Value::known(Fp::from(200).into()),
+        };
+        let search = search_k(&circuit, &[], 12);
+
+        assert_eq!(search.k, Some(10));
+        assert!(matches!(search.rejected.last(), Some((9, Rejection::NotEnoughRows { needed: Some(511), .. }))));
+    }
+
+    #[test]
+    fn test_search_k_instance_length() {
+        // Two additions fit in k = 4, 20 public values do not
+        let circuit = ThreeColumnFiboCircuit { a: Value::known(Fp::one()), b: Value::known(Fp::one()), steps: 2 };
+        let mut instances = PublicInputs::new(Fp::one(), Fp::one(), 2).instances();
+        instances[0].resize(20, Fp::zero());
+
+        let search = search_k(&circuit, &instances, 10);
+        assert_eq!(search.k, Some(5));
+        assert!(matches!(search.rejected.last(), Some((4, Rejection::InstanceTooLarge { len: 20, .. }))));
+    }
+
+    #[test]
+    fn test_search_k_unsatisfied() {
+        let circuit = ThreeColumnFiboCircuit { a: Value::known(Fp::one()), b: Value::known(Fp::one()), steps: 8 };
+        let mut public_inputs = PublicInputs::new(Fp::one(), Fp::one(), 8);
+        public_inputs.out += Fp::one();
+
+        let search = search_k(&circuit, &public_inputs.instances(), 10);
+        assert_eq!(search.k, None);
+        // The search ends at the first k the circuit fits in
+        assert!(matches!(search.rejected.last(), Some((4, Rejection::Unsatisfied(_)))));
+    }
+}
@@ -0,0 +1,229 @@
+// Native witness generation for the example circuits.
+//
+// The values a circuit assigns are computed here, outside any region closure, and handed
+// to `synthesize`. The chips then only copy them into cells, so a wrong witness shows up
+// in these plain functions (and their tests against an independent big-integer reference)
+// instead of as an unsatisfied constraint.
+//
+// Field elements are exported to JSON in the same `0x...` form the CLI prints.
+
+use halo2_proofs::arithmetic::FieldExt;
+use serde_json::{json, Value as Json};
+
+use crate::{fibonacci, is_zero};
+
+fn field_json<F: FieldExt>(value: &F) -> Json {
+    json!(format!("{:?}", value))
+}
+
+// The whole sequence F(0) = a, F(1) = b, ..., F(steps + 1)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FibonacciWitness<F> {
+    pub values: Vec<F>,
+}
+
+impl<F: FieldExt> FibonacciWitness<F> {
+    pub fn generate(a: F, b: F, steps: usize) -> Self {
+        let mut values = Vec::with_capacity(steps + 2);
+        values.push(a);
+        values.push(b);
+        for i in 0..steps {
+            values.push(values[i] + values[i + 1]);
+        }
+        Self { values }
+    }
+
+    // Number of additions
+    pub fn steps(&self) -> usize {
+        self.values.len() - 2
+    }
+
+    // Row i of the W column layout, holding F((W - 2) i) up to F((W - 2) i + W - 1). Rows
+    // overlap in two values; with W = 3 row i holds F(i), F(i + 1) and F(i + 2).
+    pub fn row<const W: usize>(&self, i: usize) -> [F; W] {
+        let start = (W - 2) * i;
+        std::array::from_fn(|column| self.values[start + column])
+    }
+
+    pub fn out(&self) -> F {
+        self.values[self.values.len() - 1]
+    }
+
+    pub fn public_inputs(&self) -> fibonacci::PublicInputs<F> {
+        fibonacci::PublicInputs {
+            a: self.values[0],
+            b: self.values[1],
+            out: self.out(),
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        json!({
+            "steps": self.steps(),
+            "values": self.values.iter().map(field_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+// A value checked against [0, range). The range-check circuits assign their value as it is,
+// so there is nothing for them to compute; this records the outcome they should have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeCheckWitness<F> {
+    pub value: F,
+    pub range: usize,
+}
+
+impl<F: FieldExt> RangeCheckWitness<F> {
+    pub fn generate(value: F, range: usize) -> Self {
+        Self { value, range }
+    }
+
+    // Whether the circuit is expected to accept the value. A value below the range fits in
+    // its lower 128 bits, and reads back as itself from them.
+    pub fn in_range(&self) -> bool {
+        let lower = self.value.get_lower_128();
+        lower < self.range as u128 && F::from_u128(lower) == self.value
+    }
+
+    pub fn to_json(&self) -> Json {
+        json!({
+            "value": field_json(&self.value),
+            "range": self.range,
+            "in_range": self.in_range(),
+        })
+    }
+}
+
+// Inputs and output of f(a, b, c) = if a == b { c } else { a - b }, with the inverse
+// the is-zero gadget uses to show that a - b is non-zero
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsZeroWitness<F> {
+    pub a: F,
+    pub b: F,
+    pub c: F,
+    // (a - b)^-1, or 0 when a == b
+    pub a_minus_b_inv: F,
+    pub out: F,
+}
+
+impl<F: FieldExt> IsZeroWitness<F> {
+    pub fn generate(a: F, b: F, c: F) -> Self {
+        let a_minus_b_inv = (a - b).invert().unwrap_or(F::zero());
+        let out = if a == b { c } else { a - b };
+        Self { a, b, c, a_minus_b_inv, out }
+    }
+
+    pub fn public_inputs(&self) -> is_zero::example1::PublicInputs<F> {
+        is_zero::example1::PublicInputs { out: self.out }
+    }
+
+    pub fn to_json(&self) -> Json {
+        json!({
+            "a": field_json(&self.a),
+            "b": field_json(&self.b),
+            "c": field_json(&self.c),
+            "a_minus_b_inv": field_json(&self.a_minus_b_inv),
+            "out": field_json(&self.out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        arithmetic::FieldExt,
+        pasta::{group::ff::PrimeField, Fp},
+    };
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    fn modulus() -> BigUint {
+        BigUint::parse_bytes(Fp::MODULUS.trim_start_matches("0x").as_bytes(), 16).unwrap()
+    }
+
+    fn to_biguint(value: &Fp) -> BigUint {
+        BigUint::from_bytes_le(value.to_repr().as_ref())
+    }
+
+    #[test]
+    fn test_fibonacci_witness_matches_bigint() {
+        // Long enough for the values to wrap around the modulus many times
+        let (a, b, steps) = (3u64, 5u64, 2000);
+        let witness = FibonacciWitness::<Fp>::generate(Fp::from(a), Fp::from(b), steps);
+        assert_eq!(witness.steps(), steps);
+
+        let p = modulus();
+        let (mut x, mut y) = (BigUint::from(a), BigUint::from(b));
+        for value in &witness.values {
+            assert_eq!(to_biguint(value), &x % &p);
+            let next = &x + &y;
+            x = y;
+            y = next;
+        }
+        assert_eq!(witness.public_inputs(), fibonacci::PublicInputs::new(Fp::from(a), Fp::from(b), steps));
+    }
+
+    #[test]
+    fn test_fibonacci_witness_json() {
+        let witness = FibonacciWitness::<Fp>::generate(Fp::one(), Fp::one(), 3);
+        let json = witness.to_json();
+        assert_eq!(json["steps"], 3);
+        assert_eq!(json["values"].as_array().unwrap().len(), 5);
+        assert_eq!(json["values"][4], format!("{:?}", Fp::from(5)));
+    }
+
+    #[test]
+    fn test_fibonacci_witness_rows() {
+        let witness = FibonacciWitness::<Fp>::generate(Fp::one(), Fp::one(), 6);
+        assert_eq!(witness.row::<3>(2), [2, 3, 5].map(Fp::from));
+        // The next row starts from the last two values of the previous one
+        assert_eq!(witness.row::<5>(0), [1, 1, 2, 3, 5].map(Fp::from));
+        assert_eq!(witness.row::<5>(1), [3, 5, 8, 13, 21].map(Fp::from));
+    }
+
+    #[test]
+    fn test_range_check_witness() {
+        assert!(RangeCheckWitness::generate(Fp::from(7), 8).in_range());
+        assert!(!RangeCheckWitness::generate(Fp::from(8), 8).in_range());
+        assert_eq!(RangeCheckWitness::generate(Fp::from(8), 8).to_json()["in_range"], false);
+    }
+
+    #[test]
+    fn test_range_check_witness_matches_bigint() {
+        // Values around the bounds, past 2^128, and just below the modulus
+        let values = [
+            Fp::zero(),
+            Fp::from(7),
+            Fp::from(255),
+            Fp::from(256),
+            Fp::from(u64::MAX),
+            Fp::from_u128(u128::MAX) + Fp::one(),
+            Fp::from_u128(1 << 127) * Fp::from(3) + Fp::from(5),
+            -Fp::one(),
+            -Fp::from(256),
+        ];
+        for value in values {
+            for range in [1, 8, 200, 256, 1 << 20] {
+                let witness = RangeCheckWitness::generate(value, range);
+                assert_eq!(witness.in_range(), to_biguint(&value) < BigUint::from(range), "{:?} < {}", value, range);
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_zero_witness_matches_bigint() {
+        let p = modulus();
+        for (a, b, c) in [(3u64, 3u64, 7u64), (10, 4, 7), (4, 10, 7)] {
+            let witness = IsZeroWitness::<Fp>::generate(Fp::from(a), Fp::from(b), Fp::from(c));
+
+            // a - b mod p, and its inverse through Fermat's little theorem
+            let diff = (BigUint::from(a) + &p - BigUint::from(b)) % &p;
+            let inv = diff.modpow(&(&p - 2u32), &p);
+            let out = if a == b { BigUint::from(c) } else { diff };
+
+            assert_eq!(to_biguint(&witness.out), out);
+            assert_eq!(to_biguint(&witness.a_minus_b_inv), inv);
+        }
+    }
+}